@@ -0,0 +1,417 @@
+use syn::meta::ParseNestedMeta;
+use syn::{Attribute, Field};
+
+#[derive(Default)]
+pub(crate) struct StructOptions {
+    pub name: Option<syn::Ident>,
+    pub doc: Option<syn::LitStr>,
+    pub vis: Option<syn::Visibility>,
+    pub typestate: bool,
+    pub constant: bool,
+    pub required_ctor: bool,
+    pub default: bool,
+    pub pattern: Option<Pattern>,
+    pub build_fn: BuildFnOptions,
+    pub setter: SetterOptions,
+    pub try_setter: Option<bool>,
+    pub env: Option<EnvOptions>,
+    pub args: bool,
+    pub derives: Vec<syn::Path>,
+    pub struct_attrs: Vec<syn::Meta>,
+    pub crate_root: Option<CrateRoot>,
+    // `crate` is a keyword.
+    pub krate: Option<syn::Path>,
+}
+
+#[derive(Default)]
+pub(crate) struct EnvOptions {
+    pub prefix: Option<syn::LitStr>,
+    pub separator: Option<syn::LitStr>,
+}
+
+#[derive(Default)]
+pub(crate) struct BuildFnOptions {
+    pub name: Option<syn::Ident>,
+    pub validate: Option<syn::Path>,
+    pub error: Option<syn::Type>,
+}
+
+// The standard library crate that generated code takes its paths from. With
+// `core`, nothing that allocates is generated.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum CrateRoot {
+    Std,
+    Alloc,
+    Core,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Pattern {
+    Mutable,
+    Owned,
+    Immutable,
+}
+
+#[derive(Default)]
+pub(crate) struct FieldOptions {
+    pub each: Option<syn::Ident>,
+    pub default: Option<DefaultValue>,
+    pub default_with: Option<syn::Expr>,
+    pub setter: SetterOptions,
+    pub try_setter: Option<bool>,
+    pub range: Option<RangeOptions>,
+    pub non_empty: bool,
+    pub matches: Option<syn::LitStr>,
+    pub sub_builder: bool,
+    pub skip: bool,
+    pub merge: Option<MergeStrategy>,
+    pub env: SourceOptions,
+    pub args: SourceOptions,
+    pub field_attrs: Vec<syn::Meta>,
+    pub doc: Option<syn::LitStr>,
+}
+
+// Field-level `env(...)` and `args(...)`, which rename the variable or flag
+// the field is read from, or skip it.
+#[derive(Default)]
+pub(crate) struct SourceOptions {
+    pub name: Option<syn::LitStr>,
+    pub skip: bool,
+}
+
+// How `merge` combines an `each` collection that is set on both sides.
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum MergeStrategy {
+    Append,
+    Replace,
+}
+
+#[derive(Default)]
+pub(crate) struct RangeOptions {
+    pub min: Option<syn::Expr>,
+    pub max: Option<syn::Expr>,
+}
+
+#[derive(Default)]
+pub(crate) struct SetterOptions {
+    pub name: Option<syn::Ident>,
+    pub prefix: Option<String>,
+    pub into: Option<bool>,
+    pub strip_option: Option<bool>,
+    pub private: Option<bool>,
+}
+
+pub(crate) enum DefaultValue {
+    Trait,
+    Expr(syn::Expr),
+}
+
+impl StructOptions {
+    pub fn from_attrs(attrs: &[Attribute]) -> syn::Result<Self> {
+        let mut options = StructOptions::default();
+
+        for attr in builder_attrs(attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("name") {
+                    let name: syn::LitStr = meta.value()?.parse()?;
+                    set_once(&meta, &mut options.name, name.parse()?)
+                } else if meta.path.is_ident("doc") {
+                    set_once(&meta, &mut options.doc, meta.value()?.parse()?)
+                } else if meta.path.is_ident("vis") {
+                    let vis: syn::LitStr = meta.value()?.parse()?;
+                    set_once(&meta, &mut options.vis, vis.parse()?)
+                } else if meta.path.is_ident("typestate") {
+                    set_flag(&meta, &mut options.typestate)
+                } else if meta.path.is_ident("const") {
+                    set_flag(&meta, &mut options.constant)
+                } else if meta.path.is_ident("required_ctor") {
+                    set_flag(&meta, &mut options.required_ctor)
+                } else if meta.path.is_ident("default") {
+                    set_flag(&meta, &mut options.default)
+                } else if meta.path.is_ident("pattern") {
+                    let value: syn::LitStr = meta.value()?.parse()?;
+                    let pattern = match value.value().as_str() {
+                        "mutable" => Pattern::Mutable,
+                        "owned" => Pattern::Owned,
+                        "immutable" => Pattern::Immutable,
+                        _ => {
+                            return Err(syn::Error::new_spanned(
+                                value,
+                                "expected `mutable`, `owned` or `immutable`",
+                            ))
+                        }
+                    };
+                    set_once(&meta, &mut options.pattern, pattern)
+                } else if meta.path.is_ident("build_fn") {
+                    options.build_fn.parse(&meta)
+                } else if meta.path.is_ident("setter") {
+                    options.setter.parse(&meta, false)
+                } else if meta.path.is_ident("try_setter") {
+                    set_once(&meta, &mut options.try_setter, parse_bool(&meta)?)
+                } else if meta.path.is_ident("env") {
+                    let mut env = EnvOptions::default();
+                    env.parse(&meta)?;
+                    set_once(&meta, &mut options.env, env)
+                } else if meta.path.is_ident("args") {
+                    set_flag(&meta, &mut options.args)
+                } else if meta.path.is_ident("derive") {
+                    meta.parse_nested_meta(|meta| {
+                        options.derives.push(meta.path);
+                        Ok(())
+                    })
+                } else if meta.path.is_ident("struct_attr") {
+                    options.struct_attrs.push(parse_attr(&meta)?);
+                    Ok(())
+                } else if meta.path.is_ident("crate_root") {
+                    let value: syn::Ident = meta.value()?.parse()?;
+                    let crate_root = match value.to_string().as_str() {
+                        "std" => CrateRoot::Std,
+                        "alloc" => CrateRoot::Alloc,
+                        "core" => CrateRoot::Core,
+                        _ => {
+                            return Err(syn::Error::new_spanned(
+                                value,
+                                "expected `std`, `alloc` or `core`",
+                            ))
+                        }
+                    };
+                    set_once(&meta, &mut options.crate_root, crate_root)
+                } else if meta.path.is_ident("crate") {
+                    let path = meta.value()?.parse()?;
+                    set_once(&meta, &mut options.krate, path)
+                } else {
+                    Err(unknown_option(&meta))
+                }
+            })?;
+        }
+
+        Ok(options)
+    }
+
+    pub fn crate_root(&self) -> CrateRoot {
+        self.crate_root.unwrap_or(CrateRoot::Std)
+    }
+
+    pub fn pattern(&self) -> Pattern {
+        match self.pattern {
+            Some(pattern) => pattern,
+            None if self.typestate || self.constant => Pattern::Owned,
+            None => Pattern::Mutable,
+        }
+    }
+}
+
+impl FieldOptions {
+    pub fn from_field(field: &Field) -> syn::Result<Self> {
+        let mut options = FieldOptions::default();
+
+        for attr in builder_attrs(&field.attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("each") {
+                    let name: syn::LitStr = meta.value()?.parse()?;
+                    set_once(&meta, &mut options.each, name.parse()?)
+                } else if meta.path.is_ident("default") {
+                    let value = if meta.input.peek(syn::Token![=]) {
+                        DefaultValue::Expr(meta.value()?.parse()?)
+                    } else {
+                        DefaultValue::Trait
+                    };
+                    set_once(&meta, &mut options.default, value)
+                } else if meta.path.is_ident("default_with") {
+                    set_once(&meta, &mut options.default_with, meta.value()?.parse()?)
+                } else if meta.path.is_ident("setter") {
+                    options.setter.parse(&meta, true)
+                } else if meta.path.is_ident("try_setter") {
+                    set_once(&meta, &mut options.try_setter, parse_bool(&meta)?)
+                } else if meta.path.is_ident("range") {
+                    let mut range = RangeOptions::default();
+                    range.parse(&meta)?;
+                    set_once(&meta, &mut options.range, range)
+                } else if meta.path.is_ident("non_empty") {
+                    set_flag(&meta, &mut options.non_empty)
+                } else if meta.path.is_ident("matches") {
+                    let pattern = meta.value()?.parse()?;
+                    set_once(&meta, &mut options.matches, pattern)
+                } else if meta.path.is_ident("sub_builder") {
+                    set_flag(&meta, &mut options.sub_builder)
+                } else if meta.path.is_ident("skip") {
+                    set_flag(&meta, &mut options.skip)
+                } else if meta.path.is_ident("merge") {
+                    let value: syn::LitStr = meta.value()?.parse()?;
+                    let strategy = match value.value().as_str() {
+                        "append" => MergeStrategy::Append,
+                        "replace" => MergeStrategy::Replace,
+                        _ => {
+                            return Err(syn::Error::new_spanned(
+                                value,
+                                "expected `append` or `replace`",
+                            ))
+                        }
+                    };
+                    set_once(&meta, &mut options.merge, strategy)
+                } else if meta.path.is_ident("env") {
+                    options.env.parse(&meta)
+                } else if meta.path.is_ident("args") {
+                    options.args.parse(&meta)
+                } else if meta.path.is_ident("doc") {
+                    set_once(&meta, &mut options.doc, meta.value()?.parse()?)
+                } else if meta.path.is_ident("field_attr") {
+                    options.field_attrs.push(parse_attr(&meta)?);
+                    Ok(())
+                } else {
+                    Err(unknown_option(&meta))
+                }
+            })?;
+        }
+
+        Ok(options)
+    }
+}
+
+impl BuildFnOptions {
+    fn parse(&mut self, meta: &ParseNestedMeta) -> syn::Result<()> {
+        meta.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                let name: syn::LitStr = meta.value()?.parse()?;
+                set_once(&meta, &mut self.name, name.parse()?)
+            } else if meta.path.is_ident("validate") {
+                let path = meta.value()?.parse()?;
+                set_once(&meta, &mut self.validate, path)
+            } else if meta.path.is_ident("error") {
+                let ty = meta.value()?.parse()?;
+                set_once(&meta, &mut self.error, ty)
+            } else {
+                Err(unknown_option(&meta))
+            }
+        })
+    }
+}
+
+impl EnvOptions {
+    fn parse(&mut self, meta: &ParseNestedMeta) -> syn::Result<()> {
+        meta.parse_nested_meta(|meta| {
+            if meta.path.is_ident("prefix") {
+                let prefix = meta.value()?.parse()?;
+                set_once(&meta, &mut self.prefix, prefix)
+            } else if meta.path.is_ident("separator") {
+                let separator = meta.value()?.parse()?;
+                set_once(&meta, &mut self.separator, separator)
+            } else {
+                Err(unknown_option(&meta))
+            }
+        })
+    }
+}
+
+impl SourceOptions {
+    fn parse(&mut self, meta: &ParseNestedMeta) -> syn::Result<()> {
+        meta.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") {
+                let name = meta.value()?.parse()?;
+                set_once(&meta, &mut self.name, name)
+            } else if meta.path.is_ident("skip") {
+                set_flag(&meta, &mut self.skip)
+            } else {
+                Err(unknown_option(&meta))
+            }
+        })
+    }
+}
+
+impl RangeOptions {
+    fn parse(&mut self, meta: &ParseNestedMeta) -> syn::Result<()> {
+        meta.parse_nested_meta(|meta| {
+            if meta.path.is_ident("min") {
+                let min = meta.value()?.parse()?;
+                set_once(&meta, &mut self.min, min)
+            } else if meta.path.is_ident("max") {
+                let max = meta.value()?.parse()?;
+                set_once(&meta, &mut self.max, max)
+            } else {
+                Err(unknown_option(&meta))
+            }
+        })?;
+
+        if self.min.is_none() && self.max.is_none() {
+            return Err(meta.error("expected `min`, `max` or both"));
+        }
+        Ok(())
+    }
+}
+
+impl SetterOptions {
+    // Struct-level setter options provide defaults for every field, so they
+    // can give a prefix for all setter names but cannot name a single setter.
+    fn parse(&mut self, meta: &ParseNestedMeta, on_field: bool) -> syn::Result<()> {
+        meta.parse_nested_meta(|meta| {
+            if meta.path.is_ident("name") && on_field {
+                let name: syn::LitStr = meta.value()?.parse()?;
+                set_once(&meta, &mut self.name, name.parse()?)
+            } else if meta.path.is_ident("prefix") && !on_field {
+                let prefix: syn::LitStr = meta.value()?.parse()?;
+                set_once(&meta, &mut self.prefix, prefix.value())
+            } else if meta.path.is_ident("into") {
+                set_once(&meta, &mut self.into, parse_bool(&meta)?)
+            } else if meta.path.is_ident("strip_option") {
+                set_once(&meta, &mut self.strip_option, parse_bool(&meta)?)
+            } else if meta.path.is_ident("private") {
+                set_once(&meta, &mut self.private, parse_bool(&meta)?)
+            } else {
+                Err(unknown_option(&meta))
+            }
+        })
+    }
+}
+
+fn builder_attrs(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("builder"))
+}
+
+// Parses either a bare flag (`into`) or an explicit `into = false`.
+fn parse_bool(meta: &ParseNestedMeta) -> syn::Result<bool> {
+    if meta.input.peek(syn::Token![=]) {
+        let value: syn::LitBool = meta.value()?.parse()?;
+        Ok(value.value)
+    } else {
+        Ok(true)
+    }
+}
+
+// The contents of `struct_attr(...)` and `field_attr(...)`, an attribute to
+// place on the generated code, e.g. `serde(default)`.
+fn parse_attr(meta: &ParseNestedMeta) -> syn::Result<syn::Meta> {
+    let content;
+    syn::parenthesized!(content in meta.input);
+    content.parse()
+}
+
+fn set_flag(meta: &ParseNestedMeta, flag: &mut bool) -> syn::Result<()> {
+    if *flag {
+        return Err(duplicate_option(meta));
+    }
+    *flag = true;
+    Ok(())
+}
+
+fn set_once<T>(meta: &ParseNestedMeta, slot: &mut Option<T>, value: T) -> syn::Result<()> {
+    if slot.is_some() {
+        return Err(duplicate_option(meta));
+    }
+    *slot = Some(value);
+    Ok(())
+}
+
+fn option_name(meta: &ParseNestedMeta) -> String {
+    meta.path
+        .get_ident()
+        .map(ToString::to_string)
+        .unwrap_or_default()
+}
+
+fn unknown_option(meta: &ParseNestedMeta) -> syn::Error {
+    meta.error(format!("unknown builder option `{}`", option_name(meta)))
+}
+
+fn duplicate_option(meta: &ParseNestedMeta) -> syn::Error {
+    meta.error(format!("duplicate builder option `{}`", option_name(meta)))
+}
@@ -0,0 +1,3549 @@
+mod attr;
+
+use attr::{CrateRoot, DefaultValue, FieldOptions, MergeStrategy, Pattern, StructOptions};
+use proc_macro::TokenStream;
+use quote::{format_ident, quote, quote_spanned};
+use syn::ext::IdentExt;
+use syn::{parse_macro_input, spanned::Spanned, DeriveInput, Field, Type};
+
+fn extract_inner_type<'a>(field: &'a Field, ident_type: &str) -> Option<&'a Type> {
+    let path = match &field.ty {
+        Type::Path(type_path) => &type_path.path,
+        _ => return None,
+    };
+
+    let segment = path.segments.first()?;
+    if segment.ident != ident_type || path.segments.len() != 1 {
+        return None;
+    }
+
+    let args = match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) => args,
+        _ => return None,
+    };
+
+    match args.args.first()? {
+        syn::GenericArgument::Type(ty) => Some(ty),
+        _ => None,
+    }
+}
+
+fn is_option_type(field: &Field) -> bool {
+    extract_inner_type(field, "Option").is_some()
+}
+
+// What the one-at-a-time setter of an `each` field adds to the collection.
+enum EachItem<'a> {
+    Value(&'a Type),
+    Entry(&'a Type, &'a Type),
+}
+
+// Collections whose item is an entry made of their first two type arguments.
+const MAP_TYPES: &[&str] = &["HashMap", "BTreeMap", "IndexMap"];
+
+// Collections of their first type argument, whatever follows it, e.g. the
+// hasher of `HashSet<T, S>`.
+const SET_TYPES: &[&str] = &[
+    "Vec",
+    "VecDeque",
+    "LinkedList",
+    "BinaryHeap",
+    "HashSet",
+    "BTreeSet",
+    "IndexSet",
+    "SmallVec",
+];
+
+// Works out the element type of a collection from its last path segment:
+// known maps such as `HashMap<K, V>` take entries, other collections their
+// first type argument, looking through arrays so that `SmallVec<[T; N]>`
+// yields `T`. An unknown type with more than one type argument could be
+// either, so it is rejected.
+fn extract_each_item(ty: &Type) -> syn::Result<EachItem<'_>> {
+    let not_a_collection = || {
+        syn::Error::new_spanned(
+            ty,
+            "`each` requires a collection type such as `Vec<T>`, `HashSet<T>` or `HashMap<K, V>`",
+        )
+    };
+    let segment = match ty {
+        Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .ok_or_else(not_a_collection)?,
+        _ => return Err(not_a_collection()),
+    };
+    let args = match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) => args,
+        _ => return Err(not_a_collection()),
+    };
+    let types: Vec<_> = args
+        .args
+        .iter()
+        .filter_map(|arg| match arg {
+            syn::GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        })
+        .collect();
+
+    let name = segment.ident.to_string();
+    match types[..] {
+        [key, value, ..] if MAP_TYPES.contains(&name.as_str()) => Ok(EachItem::Entry(key, value)),
+        _ if MAP_TYPES.contains(&name.as_str()) => Err(not_a_collection()),
+        [_, _, ..] if !SET_TYPES.contains(&name.as_str()) => Err(syn::Error::new_spanned(
+            ty,
+            format!(
+                "`each` cannot tell whether `{}` is a map or a collection of its first type \
+                 argument; only `HashMap`, `BTreeMap` and `IndexMap` are known maps",
+                name
+            ),
+        )),
+        [Type::Array(array), ..] => Ok(EachItem::Value(&array.elem)),
+        [first, ..] => Ok(EachItem::Value(first)),
+        [] => Err(not_a_collection()),
+    }
+}
+
+// A field whose type derives Builder itself and is filled in through its own
+// builder, e.g. `ServerBuilder` for a field of type `Server`.
+struct SubBuilder {
+    // `<Ty as SubBuilder>`, the hidden trait of the derive_builder crate
+    // through which the parent reaches the child's builder whatever it is
+    // named. Spanned on the field type, where a child without one is
+    // reported.
+    support: proc_macro2::TokenStream,
+    builder_ty: proc_macro2::TokenStream,
+    error_ty: proc_macro2::TokenStream,
+    // Variant of the parent's error type that wraps the child's error.
+    variant: syn::Ident,
+}
+
+impl SubBuilder {
+    fn new(
+        field: &Field,
+        name: &syn::Ident,
+        krate: &proc_macro2::TokenStream,
+    ) -> syn::Result<Self> {
+        if extract_inner_type(field, "Option").is_some() {
+            return Err(syn::Error::new_spanned(
+                &field.ty,
+                "`sub_builder` requires a struct type that derives Builder",
+            ));
+        }
+        let ty = &field.ty;
+        let support = quote_spanned! {ty.span()=>
+            <#ty as #krate::__private::SubBuilder>
+        };
+
+        let variant = syn::Ident::new(&to_upper_camel_case(name), name.span());
+        if ERROR_VARIANTS.iter().any(|reserved| variant == reserved) {
+            return Err(syn::Error::new_spanned(
+                name,
+                format!(
+                    "`sub_builder` field name clashes with the error variant `{}`",
+                    variant
+                ),
+            ));
+        }
+
+        Ok(SubBuilder {
+            builder_ty: quote! { #support::Builder },
+            error_ty: quote! { #support::Error },
+            support,
+            variant,
+        })
+    }
+}
+
+// Variants that the generated error types may use for their own purposes.
+const ERROR_VARIANTS: &[&str] = &[
+    "MissingField",
+    "ValidationFailed",
+    "DefaultFailed",
+    "OutOfRange",
+    "Empty",
+    "PatternMismatch",
+    "InvalidEnv",
+    "UnknownArgument",
+    "MissingValue",
+    "InvalidArgument",
+    "HelpRequested",
+    "Multiple",
+];
+
+struct BuilderField<'a> {
+    field: &'a Field,
+    // Name of the builder's storage for the field. Positional fields are
+    // called `_0`, `_1`, ... unless their setter is given a name.
+    name: syn::Ident,
+    member: syn::Member,
+    setter_name: syn::Ident,
+    setter_vis: syn::Visibility,
+    setter_into: bool,
+    strip_option: bool,
+    try_setter: bool,
+    each_item: Option<EachItem<'a>>,
+    sub_builder: Option<SubBuilder>,
+    // Skipped fields have no storage or setter in the builder and are always
+    // built from their default.
+    skip: bool,
+    // With #[builder(required_ctor)], required fields are also arguments of
+    // `builder_with()`, whose builder stores them without an `Option`.
+    ctor_arg: bool,
+    // `#[cfg]` attributes of the field, repeated on everything generated for
+    // it so that cfg-gated fields compile.
+    cfgs: Vec<syn::Attribute>,
+    // Docs for the setters: the field's own doc comment unless the builder
+    // is given its own wording with #[builder(doc = "...")].
+    docs: Vec<syn::Attribute>,
+    options: FieldOptions,
+    default: Option<proc_macro2::TokenStream>,
+}
+
+fn has_setter_options(options: &FieldOptions) -> bool {
+    let setter = &options.setter;
+    setter.name.is_some()
+        || setter.into.is_some()
+        || setter.strip_option.is_some()
+        || setter.private.is_some()
+        || options.try_setter.is_some()
+}
+
+impl<'a> BuilderField<'a> {
+    fn new(field: &'a Field, index: usize, struct_options: &StructOptions) -> syn::Result<Self> {
+        let options = FieldOptions::from_field(field)?;
+
+        let (name, member) = match &field.ident {
+            Some(ident) => (ident.clone(), syn::Member::Named(ident.clone())),
+            None => {
+                let name = match &options.setter.name {
+                    Some(name) => name.clone(),
+                    None => syn::Ident::new(&format!("_{}", index), field.ty.span()),
+                };
+                (name, syn::Member::Unnamed(index.into()))
+            }
+        };
+        let setter = &struct_options.setter;
+        let setter_name = match (&options.setter.name, &setter.prefix) {
+            (Some(setter_name), _) => setter_name.clone(),
+            (None, Some(prefix)) => {
+                syn::Ident::new(&format!("{}{}", prefix, name.unraw()), name.span())
+            }
+            (None, None) => name.clone(),
+        };
+        let setter_vis = match options.setter.private.or(setter.private) {
+            Some(true) => syn::Visibility::Inherited,
+            _ => syn::parse_quote!(pub),
+        };
+        let setter_into = options.setter.into.or(setter.into).unwrap_or(false);
+        let strip_option = options
+            .setter
+            .strip_option
+            .or(setter.strip_option)
+            .unwrap_or(true);
+        let try_setter = options
+            .try_setter
+            .or(struct_options.try_setter)
+            .unwrap_or(false);
+
+        let each_item = match &options.each {
+            Some(_) => Some(extract_each_item(&field.ty)?),
+            None => None,
+        };
+
+        if options.merge.is_some() && options.each.is_none() {
+            return Err(syn::Error::new_spanned(
+                field,
+                "`merge` only applies to fields with `each`",
+            ));
+        }
+
+        let sub_builder = match options.sub_builder {
+            true if options.each.is_some() || options.default.is_some() => {
+                return Err(syn::Error::new_spanned(
+                    field,
+                    "`sub_builder` cannot be combined with `each` or `default`",
+                ));
+            }
+            true => Some(SubBuilder::new(field, &name, &crate_path(struct_options))?),
+            false => None,
+        };
+
+        if options.skip
+            && (options.each.is_some() || options.sub_builder || has_setter_options(&options))
+        {
+            return Err(syn::Error::new_spanned(
+                field,
+                "`skip` fields have no setter and cannot use setter, `each` or `sub_builder` options",
+            ));
+        }
+
+        if options.default_with.is_some()
+            && (options.default.is_some()
+                || options.each.is_some()
+                || options.sub_builder
+                || options.skip)
+        {
+            return Err(syn::Error::new_spanned(
+                field,
+                "`default_with` cannot be combined with `default`, `each`, `sub_builder` or `skip`",
+            ));
+        }
+
+        let default = match &options.default {
+            _ if options.default_with.is_some() => None,
+            Some(DefaultValue::Expr(expr)) => Some(quote! { #expr }),
+            Some(DefaultValue::Trait) => Some(quote! { ::core::default::Default::default() }),
+            None if options.skip || options.each.is_some() || struct_options.default => {
+                Some(quote! { ::core::default::Default::default() })
+            }
+            None => None,
+        };
+        let ctor_arg = struct_options.required_ctor
+            && !is_option_type(field)
+            && default.is_none()
+            && options.default_with.is_none()
+            && sub_builder.is_none();
+
+        Ok(BuilderField {
+            field,
+            name,
+            member,
+            setter_name,
+            setter_vis,
+            setter_into,
+            strip_option,
+            try_setter,
+            each_item,
+            sub_builder,
+            skip: options.skip,
+            ctor_arg,
+            cfgs: field
+                .attrs
+                .iter()
+                .filter(|attr| attr.path().is_ident("cfg"))
+                .cloned()
+                .collect(),
+            docs: match &options.doc {
+                Some(doc) => vec![syn::parse_quote!(#[doc = #doc])],
+                None => field
+                    .attrs
+                    .iter()
+                    .filter(|attr| attr.path().is_ident("doc"))
+                    .cloned()
+                    .collect(),
+            },
+            options,
+            default,
+        })
+    }
+
+    fn is_required(&self) -> bool {
+        !is_option_type(self.field) && !self.is_defaulted() && self.sub_builder.is_none()
+    }
+
+    // Whether a missing value is filled in by `default` or `default_with`.
+    fn is_defaulted(&self) -> bool {
+        self.default.is_some() || self.options.default_with.is_some()
+    }
+
+    // Fields with a private setter may only be set by methods written on the
+    // builder, so they are not read from arguments or the environment unless
+    // they are given a name there explicitly.
+    fn is_private(&self) -> bool {
+        matches!(self.setter_vis, syn::Visibility::Inherited)
+    }
+
+    fn is_stored(&self) -> bool {
+        !self.skip
+    }
+
+    // Attributes for every method generated for the field.
+    fn method_attrs(&self) -> proc_macro2::TokenStream {
+        let cfgs = &self.cfgs;
+        let docs = &self.docs;
+        quote! {
+            #(#cfgs)*
+            #(#docs)*
+        }
+    }
+
+    fn has_checks(&self) -> bool {
+        self.options.range.is_some() || self.options.non_empty || self.options.matches.is_some()
+    }
+}
+
+// The type the builder stores a field as.
+fn storage_type(field: &BuilderField) -> proc_macro2::TokenStream {
+    let ty = &field.field.ty;
+    if let Some(sub_builder) = &field.sub_builder {
+        let builder_ty = &sub_builder.builder_ty;
+        quote! { #builder_ty }
+    } else if is_option_type(field.field) {
+        quote! { #ty }
+    } else {
+        quote! { ::core::option::Option<#ty> }
+    }
+}
+
+fn generate_builder_field(field: &BuilderField) -> proc_macro2::TokenStream {
+    generate_builder_field_with_type(field, storage_type(field))
+}
+
+fn generate_builder_field_with_type(
+    field: &BuilderField,
+    ty: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let name = &field.name;
+    let cfgs = &field.cfgs;
+    let attrs = &field.options.field_attrs;
+    quote! {
+        #(#cfgs)*
+        #(#[#attrs])*
+        #name: #ty
+    }
+}
+
+// Wraps the statements that update a builder into a setter-style method for
+// the given pattern. `update` receives the expression naming the builder that
+// is being modified.
+fn generate_setter(
+    pattern: Pattern,
+    attrs: &proc_macro2::TokenStream,
+    vis: &syn::Visibility,
+    name: &syn::Ident,
+    args: proc_macro2::TokenStream,
+    update: impl FnOnce(proc_macro2::TokenStream) -> proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    match pattern {
+        Pattern::Mutable => {
+            let update = update(quote! { self });
+            quote! {
+                #attrs
+                #vis fn #name(&mut self, #args) -> &mut Self {
+                    #update
+                    self
+                }
+            }
+        }
+        Pattern::Owned => {
+            let update = update(quote! { self });
+            quote! {
+                #attrs
+                #vis fn #name(mut self, #args) -> Self {
+                    #update
+                    self
+                }
+            }
+        }
+        Pattern::Immutable => {
+            let update = update(quote! { __builder });
+            quote! {
+                #attrs
+                #vis fn #name(&self, #args) -> Self {
+                    let mut __builder = ::core::clone::Clone::clone(self);
+                    #update
+                    __builder
+                }
+            }
+        }
+    }
+}
+
+// The parameter type of a setter that stores a `ty` and the expression that
+// converts the parameter into it.
+fn generate_setter_arg(
+    field: &BuilderField,
+    arg: &syn::Ident,
+    ty: &Type,
+) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    if field.setter_into {
+        (
+            quote! { impl ::core::convert::Into<#ty> },
+            quote! { ::core::convert::Into::into(#arg) },
+        )
+    } else {
+        (quote! { #ty }, quote! { #arg })
+    }
+}
+
+// The type a setter ultimately stores: optional fields take the inner value
+// unless `strip_option` is turned off.
+fn setter_value_type<'a>(field: &'a BuilderField) -> &'a Type {
+    match extract_inner_type(field.field, "Option") {
+        Some(inner_ty) if field.strip_option => inner_ty,
+        _ => &field.field.ty,
+    }
+}
+
+fn generate_try_setter(
+    field: &BuilderField,
+    receiver: proc_macro2::TokenStream,
+    output: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let name = &field.name;
+    let setter_name = &field.setter_name;
+    let try_name = syn::Ident::new(&format!("try_{}", setter_name), setter_name.span());
+    let ty = match extract_inner_type(field.field, "Option") {
+        Some(inner_ty) => inner_ty,
+        None => &field.field.ty,
+    };
+    let mut value = quote! { ::core::convert::TryInto::try_into(#name)? };
+    if is_option_type(field.field) && !field.strip_option {
+        value = quote! { ::core::option::Option::Some(#value) };
+    }
+
+    let vis = &field.setter_vis;
+    let attrs = field.method_attrs();
+    quote! {
+        #attrs
+        #vis fn #try_name<__V: ::core::convert::TryInto<#ty>>(
+            #receiver,
+            #name: __V,
+        ) -> ::core::result::Result<#output, __V::Error> {
+            ::core::result::Result::Ok(self.#setter_name(#value))
+        }
+    }
+}
+
+// The one-at-a-time setter of an `each` field, plus `extend_<name>` which adds
+// every item of an iterator. Both work with any collection that implements
+// `Default` and `Extend`.
+fn generate_each_setters(
+    field: &BuilderField,
+    pattern: Pattern,
+    each_method: &syn::Ident,
+    item: &EachItem,
+) -> proc_macro2::TokenStream {
+    let name = &field.name;
+    let (args, value, item_ty) = match item {
+        EachItem::Value(ty) => {
+            let (arg_ty, value) = generate_setter_arg(field, each_method, ty);
+            (quote! { #each_method: #arg_ty }, value, quote! { #ty })
+        }
+        EachItem::Entry(key_ty, value_ty) => {
+            let key = syn::Ident::new("key", each_method.span());
+            let value = syn::Ident::new("value", each_method.span());
+            let (key_arg, key_value) = generate_setter_arg(field, &key, key_ty);
+            let (value_arg, value_value) = generate_setter_arg(field, &value, value_ty);
+            (
+                quote! { #key: #key_arg, #value: #value_arg },
+                quote! { (#key_value, #value_value) },
+                quote! { (#key_ty, #value_ty) },
+            )
+        }
+    };
+
+    let vis = &field.setter_vis;
+    let attrs = field.method_attrs();
+    let setter = generate_setter(pattern, &attrs, vis, each_method, args, |builder| {
+        quote! {
+            ::core::iter::Extend::extend(
+                #builder.#name.get_or_insert_with(::core::default::Default::default),
+                ::core::iter::once(#value),
+            );
+        }
+    });
+    let extend_name = syn::Ident::new(
+        &format!("extend_{}", field.setter_name),
+        field.setter_name.span(),
+    );
+    let extend = generate_setter(
+        pattern,
+        &attrs,
+        vis,
+        &extend_name,
+        quote! { items: impl ::core::iter::IntoIterator<Item = #item_ty> },
+        |builder| {
+            quote! {
+                ::core::iter::Extend::extend(
+                    #builder.#name.get_or_insert_with(::core::default::Default::default),
+                    items,
+                );
+            }
+        },
+    );
+
+    quote! {
+        #setter
+        #extend
+    }
+}
+
+fn generate_setter_method(field: &BuilderField, pattern: Pattern) -> proc_macro2::TokenStream {
+    let name = &field.name;
+    let vis = &field.setter_vis;
+    let attrs = field.method_attrs();
+
+    if field.skip {
+        return proc_macro2::TokenStream::new();
+    }
+    if let (Some(each_method), Some(item)) = (&field.options.each, &field.each_item) {
+        return generate_each_setters(field, pattern, each_method, item);
+    }
+    if let Some(sub_builder) = &field.sub_builder {
+        // Sub-builders are modified in place whatever the pattern, so that
+        // `parent.child().field(..)` works without rebuilding the parent.
+        let setter_name = &field.setter_name;
+        let builder_ty = &sub_builder.builder_ty;
+        return quote! {
+            #attrs
+            #vis fn #setter_name(&mut self) -> &mut #builder_ty {
+                &mut self.#name
+            }
+        };
+    }
+
+    let ty = setter_value_type(field);
+    let (arg_ty, mut value) = generate_setter_arg(field, name, ty);
+    if !is_option_type(field.field) || field.strip_option {
+        value = quote! { ::core::option::Option::Some(#value) };
+    }
+    let setter = generate_setter(
+        pattern,
+        &attrs,
+        vis,
+        &field.setter_name,
+        quote! { #name: #arg_ty },
+        |builder| {
+            quote! {
+                #builder.#name = #value;
+            }
+        },
+    );
+    let try_setter = field.try_setter.then(|| {
+        let (receiver, output) = match pattern {
+            Pattern::Mutable => (quote! { &mut self }, quote! { &mut Self }),
+            Pattern::Owned => (quote! { self }, quote! { Self }),
+            Pattern::Immutable => (quote! { &self }, quote! { Self }),
+        };
+        generate_try_setter(field, receiver, output)
+    });
+
+    quote! {
+        #setter
+        #try_setter
+    }
+}
+
+// The name of a field inside generated method names. Positional fields get
+// `is_0_set()` rather than `is__0_set()`.
+fn field_label(field: &BuilderField) -> String {
+    let name = field.name.unraw().to_string();
+    match field.field.ident {
+        Some(_) => name,
+        None => name.trim_start_matches('_').to_owned(),
+    }
+}
+
+// `get_<field>()`, `clear_<field>()` and `reset()`, which read back and undo
+// what the setters did. Clearing a sub-builder replaces it with a new one.
+// With `constant`, every method is a `const fn` on an owned builder.
+fn generate_accessors<'f, 'a: 'f>(
+    fields: impl IntoIterator<Item = &'f BuilderField<'a>>,
+    pattern: Pattern,
+    constant: bool,
+) -> proc_macro2::TokenStream {
+    let fields: Vec<_> = fields
+        .into_iter()
+        .filter(|field| field.is_stored())
+        .collect();
+    let constness = constant.then(|| quote! { const });
+    let clear =
+        |name: &syn::Ident,
+         attrs: proc_macro2::TokenStream,
+         vis: &syn::Visibility,
+         update: &dyn Fn(proc_macro2::TokenStream) -> proc_macro2::TokenStream| {
+            if constant {
+                let update = update(quote! { self });
+                quote! {
+                    #attrs
+                    #vis const fn #name(mut self) -> Self {
+                        #update
+                        self
+                    }
+                }
+            } else {
+                generate_setter(pattern, &attrs, vis, name, quote! {}, update)
+            }
+        };
+
+    let methods = fields.iter().map(|field| {
+        let name = &field.name;
+        let cfgs = &field.cfgs;
+        let label = field_label(field);
+        let getter_name = format_ident!("get_{}", label);
+        let getter_doc = format!("The value set for `{}`, if any.", name.unraw());
+        let getter = match &field.sub_builder {
+            Some(sub_builder) => {
+                let builder_ty = &sub_builder.builder_ty;
+                let doc = format!("The builder for `{}`.", name.unraw());
+                quote! {
+                    #(#cfgs)*
+                    #[doc = #doc]
+                    pub #constness fn #getter_name(&self) -> &#builder_ty {
+                        &self.#name
+                    }
+                }
+            }
+            None => {
+                let ty = match extract_inner_type(field.field, "Option") {
+                    Some(inner_ty) => inner_ty,
+                    None => &field.field.ty,
+                };
+                quote! {
+                    #(#cfgs)*
+                    #[doc = #getter_doc]
+                    pub #constness fn #getter_name(&self) -> ::core::option::Option<&#ty> {
+                        self.#name.as_ref()
+                    }
+                }
+            }
+        };
+        let clear_name = format_ident!("clear_{}", label);
+        let clear_doc = format!(
+            "Unsets `{}`, as if its setter had never been called.",
+            name.unraw()
+        );
+        let value = generate_empty_value(field);
+        let clear = clear(
+            &clear_name,
+            quote! {
+                #(#cfgs)*
+                #[doc = #clear_doc]
+            },
+            &field.setter_vis,
+            &|builder| quote! { #builder.#name = #value; },
+        );
+        quote! {
+            #getter
+            #clear
+        }
+    });
+    let reset = clear(
+        &syn::Ident::new("reset", proc_macro2::Span::call_site()),
+        quote! {
+            /// Unsets every field, as if the builder had just been created.
+        },
+        &syn::parse_quote!(pub),
+        &|builder| {
+            let resets = fields.iter().map(|field| {
+                let name = &field.name;
+                let cfgs = &field.cfgs;
+                let value = generate_empty_value(field);
+                quote! {
+                    #(#cfgs)*
+                    {
+                        #builder.#name = #value;
+                    }
+                }
+            });
+            quote! { #(#resets)* }
+        },
+    );
+
+    quote! {
+        #(#methods)*
+        #reset
+    }
+}
+
+// The expression for a field's value in build(). Errors are pushed to
+// `__errors` so that build() can report all of them at once; fields that can
+// fail are then held as an `Option` until every error has been seen, which is
+// what the returned flag tells.
+fn generate_field_value(
+    builder: &Builder,
+    field: &BuilderField,
+    value: proc_macro2::TokenStream,
+) -> (proc_macro2::TokenStream, bool) {
+    let field_name = field.name.unraw().to_string();
+    let error_name = &builder.error_name;
+    let collect = builder.alloc_root().is_some();
+
+    if let (true, Some(default)) = (field.skip, &field.default) {
+        return (default.clone(), false);
+    }
+    // A `default_with` field is only unset here if computing it failed,
+    // which has already been reported. Without an allocator build() returned
+    // then, and the field is treated as required below.
+    if field.options.default_with.is_some() && !is_option_type(field.field) && collect {
+        return (value, true);
+    }
+    if let Some(sub_builder) = &field.sub_builder {
+        let variant = &sub_builder.variant;
+        if !collect {
+            let value = quote! {
+                match #value {
+                    ::core::result::Result::Ok(value) => value,
+                    ::core::result::Result::Err(err) => {
+                        return ::core::result::Result::Err(#error_name::#variant(err));
+                    }
+                }
+            };
+            return (value, false);
+        }
+        let value = quote! {
+            #value.map_err(|err| __errors.push(#error_name::#variant(err))).ok()
+        };
+        return (value, true);
+    }
+
+    match &field.default {
+        Some(default) if is_option_type(field.field) => (
+            quote! {
+                #value.or_else(|| #default)
+            },
+            false,
+        ),
+        None if is_option_type(field.field) => (value, false),
+        Some(default) => (
+            quote! {
+                #value.unwrap_or_else(|| #default)
+            },
+            false,
+        ),
+        None if !collect => (
+            quote! {
+                match #value {
+                    ::core::option::Option::Some(value) => value,
+                    ::core::option::Option::None => {
+                        return ::core::result::Result::Err(#error_name::MissingField {
+                            name: #field_name,
+                        });
+                    }
+                }
+            },
+            false,
+        ),
+        None => (
+            quote! {
+                #value.or_else(|| {
+                    __errors.push(#error_name::MissingField {
+                        name: #field_name,
+                    });
+                    ::core::option::Option::None
+                })
+            },
+            true,
+        ),
+    }
+}
+
+// The builder's storage for the field in build(), moved out of `receiver`.
+// Sub-builders are built through `SubBuilder::__build`, which works whatever
+// the pattern and build() name of the child.
+fn generate_build_value(
+    field: &BuilderField,
+    receiver: &proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let name = &field.name;
+    if let Some(SubBuilder { support, .. }) = &field.sub_builder {
+        return quote! { #support::__build(#receiver.#name) };
+    }
+    quote! { #receiver.#name }
+}
+
+// Declarative constraints run against the resolved value of the field, which
+// is bound to a local of the same name. Optional fields are only checked when
+// they hold a value.
+fn generate_field_checks(
+    builder: &Builder,
+    field: &BuilderField,
+    collected: bool,
+) -> Option<proc_macro2::TokenStream> {
+    if !field.has_checks() {
+        return None;
+    }
+    let error_name = &builder.error_name;
+    let name = &field.name;
+    let field_name = name.unraw().to_string();
+    let mut checks = Vec::new();
+
+    if let Some(range) = &field.options.range {
+        let min = range.min.as_ref().map(|min| quote! { #min <= *__value });
+        let max = range.max.as_ref().map(|max| quote! { *__value <= #max });
+        let conditions = min.iter().chain(&max);
+        let describe = |bound: Option<&syn::Expr>| {
+            bound
+                .map(|bound| quote! { #bound }.to_string())
+                .unwrap_or_default()
+        };
+        let range = match &range.max {
+            Some(_) => format!(
+                "{}..={}",
+                describe(range.min.as_ref()),
+                describe(range.max.as_ref())
+            ),
+            None => format!("{}..", describe(range.min.as_ref())),
+        };
+        let report = builder.report_error(quote! {
+            #error_name::OutOfRange {
+                field: #field_name,
+                range: #range,
+            }
+        });
+        checks.push(quote! {
+            if !(#(#conditions)&&*) {
+                #report
+            }
+        });
+    }
+
+    if field.options.non_empty {
+        let report = builder.report_error(quote! {
+            #error_name::Empty {
+                field: #field_name,
+            }
+        });
+        checks.push(quote! {
+            if __value.is_empty() {
+                #report
+            }
+        });
+    }
+
+    if let Some(pattern) = &field.options.matches {
+        let report = builder.report_error(quote! {
+            #error_name::PatternMismatch {
+                field: #field_name,
+                pattern: #pattern,
+            }
+        });
+        checks.push(quote! {
+            if !Self::__glob_match(#pattern, ::core::convert::AsRef::<str>::as_ref(__value)) {
+                #report
+            }
+        });
+    }
+
+    let cfgs = &field.cfgs;
+    let local = local_name(field);
+    if collected || is_option_type(field.field) {
+        Some(quote! {
+            #(#cfgs)*
+            if let ::core::option::Option::Some(__value) = &#local {
+                #(#checks)*
+            }
+        })
+    } else {
+        Some(quote! {
+            #(#cfgs)*
+            {
+                let __value = &#local;
+                #(#checks)*
+            }
+        })
+    }
+}
+
+// Matches `text` against a glob where `*` stands for any run of characters
+// and `?` for exactly one.
+fn generate_glob_match() -> proc_macro2::TokenStream {
+    quote! {
+        fn __glob_match(pattern: &str, text: &str) -> bool {
+            // `p` and `t` are the remaining pattern and text, `star` where to
+            // resume after the last `*` if the text stops matching.
+            let (mut p, mut t) = (pattern, text);
+            let mut star = ::core::option::Option::None;
+            while let ::core::option::Option::Some(c) = t.chars().next() {
+                match p.chars().next() {
+                    ::core::option::Option::Some('*') => {
+                        p = &p[1..];
+                        star = ::core::option::Option::Some((p, t));
+                    }
+                    ::core::option::Option::Some(pc) if pc == '?' || pc == c => {
+                        p = &p[pc.len_utf8()..];
+                        t = &t[c.len_utf8()..];
+                    }
+                    _ => match star {
+                        ::core::option::Option::Some((star_p, star_t)) => {
+                            let skipped = star_t.chars().next().map_or(0, char::len_utf8);
+                            p = star_p;
+                            t = &star_t[skipped..];
+                            star = ::core::option::Option::Some((star_p, t));
+                        }
+                        ::core::option::Option::None => return false,
+                    },
+                }
+            }
+            p.chars().all(|c| c == '*')
+        }
+    }
+}
+
+// The local that build() resolves a field into. It has mixed-site hygiene so
+// that it does not shadow functions or constants of the same name in the
+// expressions of later fields, such as `default = host()`.
+fn local_name(field: &BuilderField) -> syn::Ident {
+    let mut local = field.name.clone();
+    local.set_span(proc_macro2::Span::mixed_site());
+    local
+}
+
+// The body of build(): validates the builder, resolves every field into a
+// local, runs the field checks and finally constructs the target value.
+fn generate_build_body(
+    builder: &Builder,
+    values: impl Iterator<Item = (proc_macro2::TokenStream, bool)>,
+    fallible: bool,
+) -> proc_macro2::TokenStream {
+    let Builder {
+        options,
+        target,
+        error_name,
+        fields,
+        ..
+    } = builder;
+
+    let alloc = builder.alloc_root();
+    let validate = options.build_fn.validate.as_ref().map(|validate| {
+        // With `error = Type` the error is kept as it is, otherwise it is
+        // turned into a message.
+        let err = match (&options.build_fn.error, &alloc) {
+            (Some(_), _) => quote! { #error_name::ValidationFailed(err) },
+            (None, Some(alloc)) => quote! {
+                #error_name::ValidationFailed {
+                    message: #alloc::string::ToString::to_string(&err),
+                }
+            },
+            (None, None) => quote! { #error_name::ValidationFailed { message: err } },
+        };
+        let report = builder.report_error(err);
+        quote! {
+            let __builder: &Self = &self;
+            if let ::core::result::Result::Err(err) = #validate(__builder) {
+                #report
+            }
+        }
+    });
+    let default_with = (!builder.default_order.is_empty()).then(|| {
+        // Like the error of `validate`, kept as it is with `error = Type`.
+        let err = match (&options.build_fn.error, &alloc) {
+            (Some(_), _) => quote! { error: err },
+            (None, Some(alloc)) => quote! { message: #alloc::string::ToString::to_string(&err) },
+            (None, None) => quote! { message: err },
+        };
+        // Plain defaults are stored first, so that the functions see the
+        // value the field will be built with.
+        let defaults = fields
+            .iter()
+            .filter(|field| !field.skip && field.sub_builder.is_none())
+            .filter_map(|field| {
+                let name = &field.name;
+                let cfgs = &field.cfgs;
+                let default = field.default.as_ref()?;
+                let value = match is_option_type(field.field) {
+                    true => quote! { #default },
+                    false => quote! { ::core::option::Option::Some(#default) },
+                };
+                Some(quote! {
+                    #(#cfgs)*
+                    if __resolved.#name.is_none() {
+                        __resolved.#name = #value;
+                    }
+                })
+            });
+        let computations = builder.default_order.iter().map(|&index| {
+            let field = &fields[index];
+            let name = &field.name;
+            let field_name = name.unraw().to_string();
+            let cfgs = &field.cfgs;
+            let default_with = &field.options.default_with;
+            let value = match is_option_type(field.field) {
+                true => quote! { value },
+                false => quote! { ::core::option::Option::Some(value) },
+            };
+            let report = builder.report_error(quote! {
+                #error_name::DefaultFailed {
+                    field: #field_name,
+                    #err,
+                }
+            });
+            quote! {
+                #(#cfgs)*
+                if __resolved.#name.is_none() {
+                    match (#default_with)(&__resolved) {
+                        ::core::result::Result::Ok(value) => __resolved.#name = #value,
+                        ::core::result::Result::Err(err) => {
+                            #report
+                        }
+                    }
+                }
+            }
+        });
+        quote! {
+            let mut __resolved = self;
+            #(#defaults)*
+            #(#computations)*
+        }
+    });
+    let (values, collected): (Vec<_>, Vec<_>) = values.unzip();
+    let locals: Vec<_> = fields
+        .iter()
+        .zip(values)
+        .map(|(field, value)| {
+            let local = local_name(field);
+            let cfgs = &field.cfgs;
+            quote! {
+                #(#cfgs)*
+                let #local = #value;
+            }
+        })
+        .collect();
+    let members = fields.iter().map(|field| {
+        let local = local_name(field);
+        let member = &field.member;
+        let cfgs = &field.cfgs;
+        quote! {
+            #(#cfgs)*
+            #member: #local
+        }
+    });
+    let checks = fields
+        .iter()
+        .zip(&collected)
+        .filter_map(|(field, &collected)| generate_field_checks(builder, field, collected));
+    let value = quote! {
+        #target {
+            #(#members,)*
+        }
+    };
+    if !fallible {
+        return quote! {
+            #(#locals)*
+            #value
+        };
+    }
+
+    let unwrap_collected = fields
+        .iter()
+        .zip(&collected)
+        .filter(|(_, &collected)| collected)
+        .map(|(field, _)| {
+            let local = local_name(field);
+            let cfgs = &field.cfgs;
+            quote! {
+                #(#cfgs)*
+                let ::core::option::Option::Some(#local) = #local else {
+                    return ::core::result::Result::Err(#error_name::__from_errors(__errors));
+                };
+            }
+        });
+    let Some(alloc) = alloc else {
+        return quote! {
+            #validate
+            #default_with
+            #(#locals)*
+            #(#checks)*
+            ::core::result::Result::Ok(#value)
+        };
+    };
+    quote! {
+        #[allow(unused_mut)]
+        let mut __errors: #alloc::vec::Vec<#error_name> = #alloc::vec::Vec::new();
+        #validate
+        #default_with
+        #(#locals)*
+        #(#checks)*
+        if !__errors.is_empty() {
+            return ::core::result::Result::Err(#error_name::__from_errors(__errors));
+        }
+        #(#unwrap_collected)*
+        ::core::result::Result::Ok(#value)
+    }
+}
+
+fn generate_error_type(builder: &Builder, missing_field: bool) -> proc_macro2::TokenStream {
+    let error_name = &builder.error_name;
+    let vis = &builder.vis;
+    let doc = format!(
+        "Error returned when a [`{}`] cannot be built.",
+        target_name(builder)
+    );
+    let alloc = builder.alloc_root();
+    let mut variants = Vec::new();
+    let mut messages = Vec::new();
+
+    // Messages about a field print the path of enclosing sub-builder fields
+    // in front of its name, e.g. "field `server.port` is not set".
+    let field_message = |field: proc_macro2::TokenStream, rest: &str| {
+        quote! {
+            f.write_str("field `")?;
+            parent(f)?;
+            ::core::write!(f, #rest, #field)
+        }
+    };
+
+    if missing_field {
+        variants.push(quote! {
+            /// A required field was not set.
+            MissingField {
+                /// Name of the field.
+                name: &'static str,
+            }
+        });
+        let message = field_message(quote! { name }, "{}` is not set");
+        messages.push(quote! {
+            #error_name::MissingField { name } => { #message }
+        });
+    }
+    if let Some(error_ty) = &builder.options.build_fn.error {
+        variants.push(quote! {
+            /// The validation function rejected the builder.
+            ValidationFailed(#error_ty)
+        });
+        messages.push(quote! {
+            #error_name::ValidationFailed(err) => ::core::fmt::Display::fmt(err, f),
+        });
+    } else if builder.options.build_fn.validate.is_some() {
+        let message_ty = match &alloc {
+            Some(alloc) => quote! { #alloc::string::String },
+            None => quote! { &'static str },
+        };
+        variants.push(quote! {
+            /// The validation function rejected the builder.
+            ValidationFailed {
+                /// The error the validation function returned.
+                message: #message_ty,
+            }
+        });
+        messages.push(quote! {
+            #error_name::ValidationFailed { message } => f.write_str(message),
+        });
+    }
+    let fields = &builder.fields;
+    if !builder.default_order.is_empty() {
+        let (member, error_ty) = match (&builder.options.build_fn.error, &alloc) {
+            (Some(error_ty), _) => (quote! { error }, quote! { #error_ty }),
+            (None, Some(alloc)) => (quote! { message }, quote! { #alloc::string::String }),
+            (None, None) => (quote! { message }, quote! { &'static str }),
+        };
+        variants.push(quote! {
+            /// The `default_with` function of a field failed.
+            DefaultFailed {
+                /// Name of the field.
+                field: &'static str,
+                /// The error the function returned.
+                #member: #error_ty,
+            }
+        });
+        let message = field_message(quote! { field, #member }, "{}` could not be computed: {}");
+        messages.push(quote! {
+            #error_name::DefaultFailed { field, #member } => { #message }
+        });
+    }
+    if fields.iter().any(|field| field.options.range.is_some()) {
+        variants.push(quote! {
+            /// A field is outside of its allowed range.
+            OutOfRange {
+                /// Name of the field.
+                field: &'static str,
+                /// The allowed range.
+                range: &'static str,
+            }
+        });
+        let message = field_message(quote! { field, range }, "{}` is out of range `{}`");
+        messages.push(quote! {
+            #error_name::OutOfRange { field, range } => { #message }
+        });
+    }
+    if fields.iter().any(|field| field.options.non_empty) {
+        variants.push(quote! {
+            /// A field that must not be empty is empty.
+            Empty {
+                /// Name of the field.
+                field: &'static str,
+            }
+        });
+        let message = field_message(quote! { field }, "{}` must not be empty");
+        messages.push(quote! {
+            #error_name::Empty { field } => { #message }
+        });
+    }
+    if fields.iter().any(|field| field.options.matches.is_some()) {
+        variants.push(quote! {
+            /// A field does not match its pattern.
+            PatternMismatch {
+                /// Name of the field.
+                field: &'static str,
+                /// The pattern the field must match.
+                pattern: &'static str,
+            }
+        });
+        let message = field_message(quote! { field, pattern }, "{}` does not match `{}`");
+        messages.push(quote! {
+            #error_name::PatternMismatch { field, pattern } => { #message }
+        });
+    }
+    if builder.options.env.is_some() {
+        variants.push(quote! {
+            /// An environment variable could not be parsed.
+            InvalidEnv {
+                /// Name of the variable.
+                var: &'static str,
+                /// Why parsing failed.
+                message: ::std::string::String,
+            }
+        });
+        messages.push(quote! {
+            #error_name::InvalidEnv { var, message } => {
+                ::core::write!(f, "environment variable `{}` is invalid: {}", var, message)
+            }
+        });
+    }
+    if builder.options.args {
+        variants.push(quote! {
+            /// An argument is not one of the known flags.
+            UnknownArgument {
+                /// The argument as it was given.
+                argument: ::std::string::String,
+            }
+        });
+        variants.push(quote! {
+            /// A flag that takes a value was given without one.
+            MissingValue {
+                /// The flag.
+                flag: &'static str,
+            }
+        });
+        variants.push(quote! {
+            /// The value of a flag could not be parsed.
+            InvalidArgument {
+                /// The flag.
+                flag: &'static str,
+                /// Why parsing failed.
+                message: ::std::string::String,
+            }
+        });
+        variants.push(quote! {
+            /// `--help` was given. The error displays the help text.
+            HelpRequested
+        });
+        let help = args_help(fields);
+        messages.push(quote! {
+            #error_name::UnknownArgument { argument } => {
+                ::core::write!(f, "unexpected argument `{}`", argument)
+            }
+            #error_name::MissingValue { flag } => {
+                ::core::write!(f, "argument `{}` requires a value", flag)
+            }
+            #error_name::InvalidArgument { flag, message } => {
+                ::core::write!(f, "argument `{}` is invalid: {}", flag, message)
+            }
+            #error_name::HelpRequested => f.write_str(#help),
+        });
+    }
+    if let Some(alloc) = &alloc {
+        variants.push(quote! {
+            /// More than one error was found.
+            Multiple(#alloc::vec::Vec<Self>)
+        });
+        messages.push(quote! {
+            #error_name::Multiple(errors) => {
+                let missing_fields = errors
+                    .iter()
+                    .filter_map(#error_name::__missing_field)
+                    .collect::<#alloc::vec::Vec<_>>();
+                if missing_fields.len() == errors.len() {
+                    f.write_str("missing fields: ")?;
+                    for (i, name) in missing_fields.iter().enumerate() {
+                        if i > 0 {
+                            f.write_str(", ")?;
+                        }
+                        parent(f)?;
+                        f.write_str(name)?;
+                    }
+                    return ::core::result::Result::Ok(());
+                }
+                for (i, err) in errors.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str("; ")?;
+                    }
+                    err.__fmt_in(f, parent)?;
+                }
+                ::core::result::Result::Ok(())
+            }
+        });
+    }
+    let missing_field_name = if missing_field {
+        quote! {
+            match self {
+                #error_name::MissingField { name } => ::core::option::Option::Some(name),
+                _ => ::core::option::Option::None,
+            }
+        }
+    } else {
+        quote! { ::core::option::Option::None }
+    };
+    for field in fields {
+        if let Some(sub_builder) = &field.sub_builder {
+            let SubBuilder {
+                support,
+                error_ty,
+                variant,
+                ..
+            } = sub_builder;
+            let prefix = format!("{}.", field.name.unraw());
+            let cfgs = &field.cfgs;
+            let doc = format!("Building the `{}` field failed.", field.name.unraw());
+            variants.push(quote! {
+                #(#cfgs)*
+                #[doc = #doc]
+                #variant(#error_ty)
+            });
+            messages.push(quote! {
+                #(#cfgs)*
+                #error_name::#variant(err) => #support::__fmt_error(err, f, &|f| {
+                    parent(f)?;
+                    f.write_str(#prefix)
+                }),
+            });
+        }
+    }
+
+    // Without an allocator build() stops at the first error, so there is
+    // never more than one.
+    let collected = alloc.as_ref().map(|alloc| {
+        quote! {
+            /// Names of the required fields that were not set.
+            pub fn missing_fields(&self) -> #alloc::vec::Vec<&'static str> {
+                self.errors()
+                    .iter()
+                    .filter_map(#error_name::__missing_field)
+                    .copied()
+                    .collect()
+            }
+
+            fn __missing_field(&self) -> ::core::option::Option<&&'static str> {
+                #missing_field_name
+            }
+
+            #[doc(hidden)]
+            pub fn __from_errors(mut errors: #alloc::vec::Vec<Self>) -> Self {
+                match errors.len() {
+                    1 => errors.remove(0),
+                    _ => #error_name::Multiple(errors),
+                }
+            }
+        }
+    });
+    let errors = match &alloc {
+        Some(_) => quote! {
+            match self {
+                #error_name::Multiple(errors) => errors,
+                _ => ::core::slice::from_ref(self),
+            }
+        },
+        None => quote! { ::core::slice::from_ref(self) },
+    };
+    let error_trait = match builder.options.crate_root() {
+        CrateRoot::Std => quote! { ::std::error::Error },
+        CrateRoot::Alloc | CrateRoot::Core => quote! { ::core::error::Error },
+    };
+
+    quote! {
+        #[derive(::core::fmt::Debug, ::core::clone::Clone, ::core::cmp::PartialEq)]
+        #[doc = #doc]
+        #vis enum #error_name {
+            #(#variants,)*
+        }
+
+        impl #error_name {
+            /// Every error that build() found, in the order they occurred.
+            pub fn errors(&self) -> &[Self] {
+                #errors
+            }
+
+            #collected
+
+            #[doc(hidden)]
+            #[allow(unused_variables)]
+            pub fn __fmt_in(
+                &self,
+                f: &mut ::core::fmt::Formatter<'_>,
+                parent: &dyn ::core::ops::Fn(&mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result,
+            ) -> ::core::fmt::Result {
+                match self {
+                    #(#messages)*
+                }
+            }
+        }
+
+        impl ::core::fmt::Display for #error_name {
+            fn fmt(&self, f: &mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result {
+                self.__fmt_in(f, &|_| ::core::result::Result::Ok(()))
+            }
+        }
+
+        impl #error_trait for #error_name {}
+    }
+}
+
+// Statements of `__merge`, which moves every field that is set in `other`
+// into `self`. The public `merge` wraps it in the builder's pattern, while
+// sub-builders are merged through `SubBuilder::__merge` so that it does not
+// matter which pattern the child uses.
+fn generate_merge_field(field: &BuilderField) -> proc_macro2::TokenStream {
+    let name = &field.name;
+    let cfgs = &field.cfgs;
+
+    let merge = if let Some(SubBuilder { support, .. }) = &field.sub_builder {
+        quote! { #support::__merge(&mut self.#name, other.#name); }
+    } else {
+        generate_merge_value(field)
+    };
+    quote! {
+        #(#cfgs)*
+        #merge
+    }
+}
+
+fn generate_merge_value(field: &BuilderField) -> proc_macro2::TokenStream {
+    let name = &field.name;
+    match (&field.each_item, field.options.merge) {
+        (Some(_), None | Some(MergeStrategy::Append)) => quote! {
+            match (&mut self.#name, other.#name) {
+                (::core::option::Option::Some(items), ::core::option::Option::Some(other)) => {
+                    ::core::iter::Extend::extend(items, other);
+                }
+                (items, other @ ::core::option::Option::Some(_)) => *items = other,
+                (_, ::core::option::Option::None) => {}
+            }
+        },
+        _ => quote! {
+            if let ::core::option::Option::Some(value) = other.#name {
+                self.#name = ::core::option::Option::Some(value);
+            }
+        },
+    }
+}
+
+// Appending needs the collection to be iterable and not just `Extend`. The
+// bound is higher-ranked so that builders of collections which are not still
+// derive, only without a usable merge().
+fn merge_bounds<'a>(
+    fields: &'a [BuilderField],
+) -> impl Iterator<Item = proc_macro2::TokenStream> + 'a {
+    fields.iter().filter_map(|field| {
+        let item = match (&field.each_item, field.options.merge) {
+            (Some(_), Some(MergeStrategy::Replace)) | (None, _) => return None,
+            (Some(EachItem::Value(ty)), _) => quote! { #ty },
+            (Some(EachItem::Entry(key_ty, value_ty)), _) => quote! { (#key_ty, #value_ty) },
+        };
+        let ty = &field.field.ty;
+        Some(quote! { for<'__b> #ty: ::core::iter::IntoIterator<Item = #item> })
+    })
+}
+
+// `from_env()` and `from_env_map()`, which set every field whose variable is
+// present by parsing it with `FromStr`. `each` fields hold a list of items
+// split on the separator, written as `KEY=VALUE` for maps. Sub-builders are
+// left alone since their variables would need a prefix of their own.
+fn generate_env_constructors(builder: &Builder) -> Option<proc_macro2::TokenStream> {
+    let env = builder.options.env.as_ref()?;
+    let Builder {
+        ast,
+        error_name,
+        constructor,
+        fields,
+        ..
+    } = builder;
+    let struct_name = &ast.ident;
+    let (_, ty_generics, _) = ast.generics.split_for_impl();
+    let prefix = env
+        .prefix
+        .as_ref()
+        .map(syn::LitStr::value)
+        .unwrap_or_default();
+    let separator = match &env.separator {
+        Some(separator) => separator.value(),
+        None => ",".to_owned(),
+    };
+
+    let parse_fields = fields.iter().filter_map(|field| {
+        if field.skip || field.options.env.skip || field.sub_builder.is_some() {
+            return None;
+        }
+        if field.is_private() && field.options.env.name.is_none() {
+            return None;
+        }
+        let name = &field.name;
+        let var = match &field.options.env.name {
+            Some(var) => var.value(),
+            None => format!("{}{}", prefix, name.unraw().to_string().to_uppercase()),
+        };
+        let ty = extract_inner_type(field.field, "Option").unwrap_or(&field.field.ty);
+        let value = match &field.each_item {
+            None => quote! { Self::__parse_env::<#ty>(#var, raw)? },
+            Some(item) => {
+                let parse_item = match item {
+                    EachItem::Value(item_ty) => quote! {
+                        Self::__parse_env::<#item_ty>(#var, item)?
+                    },
+                    EachItem::Entry(key_ty, value_ty) => quote! {
+                        match item.split_once('=') {
+                            ::core::option::Option::Some((key, value)) => (
+                                Self::__parse_env::<#key_ty>(#var, key.trim())?,
+                                Self::__parse_env::<#value_ty>(#var, value.trim())?,
+                            ),
+                            ::core::option::Option::None => {
+                                return ::core::result::Result::Err(#error_name::InvalidEnv {
+                                    var: #var,
+                                    message: ::std::format!("expected `KEY=VALUE`, found `{}`", item),
+                                });
+                            }
+                        }
+                    },
+                };
+                quote! {{
+                    let mut items: #ty = ::core::default::Default::default();
+                    for item in raw.split(#separator).map(str::trim).filter(|item| !item.is_empty()) {
+                        ::core::iter::Extend::extend(&mut items, ::core::iter::once(#parse_item));
+                    }
+                    items
+                }}
+            }
+        };
+        let cfgs = &field.cfgs;
+        Some(quote! {
+            #(#cfgs)*
+            if let ::core::option::Option::Some(raw) = vars.get(#var) {
+                builder.#name = ::core::option::Option::Some(#value);
+            }
+        })
+    });
+
+    Some(quote! {
+        /// Creates a builder from the variables of the process environment.
+        pub fn from_env() -> ::core::result::Result<Self, #error_name> {
+            let vars: ::std::collections::HashMap<::std::string::String, ::std::string::String> =
+                ::std::env::vars_os()
+                    .filter_map(|(key, value)| {
+                        ::core::option::Option::Some((key.into_string().ok()?, value.into_string().ok()?))
+                    })
+                    .collect();
+            Self::from_env_map(&vars)
+        }
+
+        /// Creates a builder from the given environment variables.
+        pub fn from_env_map(
+            vars: &::std::collections::HashMap<::std::string::String, ::std::string::String>,
+        ) -> ::core::result::Result<Self, #error_name> {
+            let mut builder = <#struct_name #ty_generics>::#constructor();
+            #(#parse_fields)*
+            ::core::result::Result::Ok(builder)
+        }
+
+        fn __parse_env<__T>(var: &'static str, raw: &str) -> ::core::result::Result<__T, #error_name>
+        where
+            __T: ::core::str::FromStr,
+            __T::Err: ::core::fmt::Display,
+        {
+            Self::__parse(raw).map_err(|message| #error_name::InvalidEnv { var, message })
+        }
+    })
+}
+
+fn is_bool_type(ty: &Type) -> bool {
+    matches!(ty, Type::Path(type_path) if type_path.qself.is_none() && type_path.path.is_ident("bool"))
+}
+
+// The lines of a doc comment, with the space after `///` removed.
+fn doc_lines(attrs: &[syn::Attribute]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for attr in attrs {
+        if let syn::Meta::NameValue(meta) = &attr.meta {
+            if let syn::Expr::Lit(syn::ExprLit {
+                lit: syn::Lit::Str(doc),
+                ..
+            }) = &meta.value
+            {
+                if meta.path.is_ident("doc") {
+                    lines.push(doc.value());
+                }
+            }
+        }
+    }
+    lines
+        .iter()
+        .flat_map(|doc| doc.split('\n'))
+        .map(|line| line.trim().to_owned())
+        .collect()
+}
+
+// The first paragraph of a field's doc comment, on a single line.
+fn doc_summary(attrs: &[syn::Attribute]) -> String {
+    doc_lines(attrs)
+        .into_iter()
+        .skip_while(|line| line.is_empty())
+        .take_while(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+// The flag a field is read from, without the leading `--`, or None if it is
+// not read from arguments at all.
+fn arg_name(field: &BuilderField) -> Option<String> {
+    if field.skip || field.options.args.skip || field.sub_builder.is_some() {
+        return None;
+    }
+    if field.is_private() && field.options.args.name.is_none() {
+        return None;
+    }
+    let setter_name = field.options.setter.name.as_ref().unwrap_or(&field.name);
+    Some(match &field.options.args.name {
+        Some(name) => name.value(),
+        None => setter_name
+            .unraw()
+            .to_string()
+            .trim_start_matches('_')
+            .replace('_', "-"),
+    })
+}
+
+fn is_flag_field(field: &BuilderField) -> bool {
+    field.options.each.is_none() && is_bool_type(setter_value_type(field))
+}
+
+fn args_help(fields: &[BuilderField]) -> String {
+    let mut options: Vec<_> = fields
+        .iter()
+        .filter_map(|field| {
+            let mut flag = format!("--{}", arg_name(field)?);
+            match &field.each_item {
+                _ if is_flag_field(field) => {}
+                Some(EachItem::Value(_)) => flag.push_str(" <VALUE>..."),
+                Some(EachItem::Entry(..)) => flag.push_str(" <KEY=VALUE>..."),
+                None => flag.push_str(" <VALUE>"),
+            }
+            Some((flag, doc_summary(&field.field.attrs)))
+        })
+        .collect();
+    options.push(("-h, --help".to_owned(), "Print this help".to_owned()));
+
+    let width = options
+        .iter()
+        .map(|(flag, _)| flag.len())
+        .max()
+        .unwrap_or(0);
+    let mut help = String::from("Options:\n");
+    for (flag, doc) in &options {
+        let line = format!("  {:width$}  {}", flag, doc, width = width);
+        help.push_str(line.trim_end());
+        help.push('\n');
+    }
+    help
+}
+
+// `from_args()`, which reads `--flag=value` and `--flag value` arguments and
+// passes each value to the field's setter, plus the `HELP` text built from
+// the field docs. Boolean fields are bare flags and `each` fields may be
+// given more than once. Flags are the setter names in kebab case.
+fn generate_args_constructor(
+    builder: &Builder,
+    pattern: Pattern,
+) -> Option<proc_macro2::TokenStream> {
+    if !builder.options.args {
+        return None;
+    }
+    let Builder {
+        ast,
+        error_name,
+        constructor,
+        fields,
+        ..
+    } = builder;
+    let struct_name = &ast.ident;
+    let (_, ty_generics, _) = ast.generics.split_for_impl();
+
+    let arms = fields.iter().filter_map(|field| {
+        let name = arg_name(field)?;
+        let flag = format!("--{}", name);
+        let parse = |ty: &Type, raw: proc_macro2::TokenStream| {
+            quote! {
+                Self::__parse::<#ty>(#raw).map_err(|message| #error_name::InvalidArgument {
+                    flag: #flag,
+                    message,
+                })?
+            }
+        };
+
+        let (setter, value) = match (&field.options.each, &field.each_item) {
+            (Some(each_method), Some(EachItem::Value(item_ty))) => {
+                (each_method, parse(item_ty, quote! { &raw }))
+            }
+            (Some(each_method), Some(EachItem::Entry(key_ty, value_ty))) => {
+                let key = parse(key_ty, quote! { key.trim() });
+                let value = parse(value_ty, quote! { value.trim() });
+                let entry = quote! {
+                    match raw.split_once('=') {
+                        ::core::option::Option::Some((key, value)) => (#key, #value),
+                        ::core::option::Option::None => {
+                            return ::core::result::Result::Err(#error_name::InvalidArgument {
+                                flag: #flag,
+                                message: ::std::format!("expected `KEY=VALUE`, found `{}`", raw),
+                            });
+                        }
+                    }
+                };
+                (each_method, entry)
+            }
+            _ => {
+                let setter_ty = setter_value_type(field);
+                match extract_inner_type(field.field, "Option") {
+                    Some(inner_ty) if !field.strip_option => {
+                        let value = parse(inner_ty, quote! { &raw });
+                        (
+                            &field.setter_name,
+                            quote! { ::core::option::Option::Some(#value) },
+                        )
+                    }
+                    _ => (&field.setter_name, parse(setter_ty, quote! { &raw })),
+                }
+            }
+        };
+        let raw = if is_flag_field(field) {
+            quote! {
+                let raw = match value {
+                    ::core::option::Option::Some(raw) => raw,
+                    ::core::option::Option::None => ::std::string::String::from("true"),
+                };
+            }
+        } else {
+            quote! {
+                let raw = match value {
+                    ::core::option::Option::Some(raw) => raw,
+                    ::core::option::Option::None => args
+                        .next()
+                        .ok_or(#error_name::MissingValue { flag: #flag })?,
+                };
+            }
+        };
+        let call = match (field.options.each.is_some(), &field.each_item) {
+            (true, Some(EachItem::Entry(..))) => quote! {{
+                let (key, value) = #value;
+                builder.#setter(key, value)
+            }},
+            _ => quote! { builder.#setter(#value) },
+        };
+        let call = match pattern {
+            Pattern::Mutable => quote! { #call; },
+            Pattern::Owned | Pattern::Immutable => quote! { builder = #call; },
+        };
+        let cfgs = &field.cfgs;
+        Some(quote! {
+            #(#cfgs)*
+            #name => {
+                #raw
+                #call
+            }
+        })
+    });
+    let help = args_help(fields);
+
+    Some(quote! {
+        /// Help text listing the flags that from_args() accepts.
+        pub const HELP: &'static str = #help;
+
+        /// Creates a builder from command-line arguments, not including the
+        /// program name.
+        pub fn from_args<__I>(args: __I) -> ::core::result::Result<Self, #error_name>
+        where
+            __I: ::core::iter::IntoIterator<Item = ::std::string::String>,
+        {
+            #[allow(unused_mut)]
+            let mut builder = <#struct_name #ty_generics>::#constructor();
+            let mut args = ::core::iter::IntoIterator::into_iter(args);
+            while let ::core::option::Option::Some(arg) = args.next() {
+                if arg == "--help" || arg == "-h" {
+                    return ::core::result::Result::Err(#error_name::HelpRequested);
+                }
+                let (flag, value) = match arg.strip_prefix("--") {
+                    ::core::option::Option::Some(flag) => match flag.split_once('=') {
+                        ::core::option::Option::Some((flag, value)) => {
+                            (flag, ::core::option::Option::Some(::std::string::String::from(value)))
+                        }
+                        ::core::option::Option::None => (flag, ::core::option::Option::None),
+                    },
+                    ::core::option::Option::None => {
+                        return ::core::result::Result::Err(#error_name::UnknownArgument { argument: arg });
+                    }
+                };
+                match flag {
+                    #(#arms)*
+                    _ => {
+                        return ::core::result::Result::Err(#error_name::UnknownArgument {
+                            argument: ::core::clone::Clone::clone(&arg),
+                        });
+                    }
+                }
+            }
+            ::core::result::Result::Ok(builder)
+        }
+    })
+}
+
+// Parses a value read from the environment or the command line.
+fn generate_parse_fn(builder: &Builder) -> Option<proc_macro2::TokenStream> {
+    if builder.options.env.is_none() && !builder.options.args {
+        return None;
+    }
+    Some(quote! {
+        fn __parse<__T>(raw: &str) -> ::core::result::Result<__T, ::std::string::String>
+        where
+            __T: ::core::str::FromStr,
+            __T::Err: ::core::fmt::Display,
+        {
+            ::core::str::FromStr::from_str(raw).map_err(|err: __T::Err| ::std::string::ToString::to_string(&err))
+        }
+    })
+}
+
+// A type as it would be written in source, e.g. `Vec<String>` rather than
+// the spacing of its tokens, `Vec < String >`.
+fn type_string(ty: &Type) -> String {
+    let mut string = quote! { #ty }.to_string();
+    for (from, to) in [
+        (" :: ", "::"),
+        (":: ", "::"),
+        (" <", "<"),
+        ("< ", "<"),
+        (" >", ">"),
+        (" ,", ","),
+        ("& ", "&"),
+        ("[ ", "["),
+        (" ]", "]"),
+        (" ;", ";"),
+        ("( ", "("),
+        (" )", ")"),
+    ] {
+        string = string.replace(from, to);
+    }
+    string
+}
+
+// `FIELDS`, describing every field that can be set with the `BuilderFieldInfo`
+// of the derive_builder crate, and the `unset_fields()` and `is_<field>_set()`
+// queries; `unset_fields()` needs an allocator. Sub-builders are described but not queried since they are never
+// unset, and neither are the required fields of typestate builders, which the
+// builder's type tracks instead. `fields_header` and `queries_header` are the
+// `impl<..> Builder<..> where ..` to put them in.
+fn generate_introspection(
+    builder: &Builder,
+    fields_header: proc_macro2::TokenStream,
+    queries_header: proc_macro2::TokenStream,
+) -> proc_macro2::TokenStream {
+    let fields: Vec<_> = builder
+        .fields
+        .iter()
+        .filter(|field| field.is_stored())
+        .collect();
+    let typestate = builder.options.typestate;
+    let krate = builder.crate_path();
+
+    let infos = fields.iter().map(|field| {
+        let name = field.name.unraw().to_string();
+        let ty = type_string(&field.field.ty);
+        let kind = if field.each_item.is_some() {
+            quote! { Each }
+        } else if field.sub_builder.is_some() {
+            quote! { SubBuilder }
+        } else if is_option_type(field.field) {
+            quote! { Optional }
+        } else if field.is_defaulted() {
+            quote! { Defaulted }
+        } else {
+            quote! { Required }
+        };
+        let doc = doc_lines(&field.docs).join("\n").trim().to_owned();
+        let cfgs = &field.cfgs;
+        quote! {
+            #(#cfgs)*
+            #krate::BuilderFieldInfo {
+                name: #name,
+                ty: #ty,
+                kind: #krate::BuilderFieldKind::#kind,
+                doc: #doc,
+            }
+        }
+    });
+    let alloc = builder.alloc_root();
+    let queried: Vec<_> = fields
+        .iter()
+        .filter(|field| field.sub_builder.is_none() && !(typestate && field.is_required()))
+        .collect();
+    let is_set = queried.iter().map(|field| {
+        let name = &field.name;
+        let cfgs = &field.cfgs;
+        let method = format_ident!("is_{}_set", field_label(field));
+        let doc = format!("Whether `{}` has been set.", name.unraw());
+        quote! {
+            #(#cfgs)*
+            #[doc = #doc]
+            pub const fn #method(&self) -> bool {
+                self.#name.is_some()
+            }
+        }
+    });
+    let unset_fields = alloc.as_ref().map(|alloc| {
+        let unset = queried.iter().map(|field| {
+            let name = &field.name;
+            let field_name = name.unraw().to_string();
+            let cfgs = &field.cfgs;
+            quote! {
+                #(#cfgs)*
+                if self.#name.is_none() {
+                    unset.push(#field_name);
+                }
+            }
+        });
+        let doc = match typestate {
+            true => quote! {
+                /// Names of the optional and defaulted fields that have not
+                /// been set. Whether required fields are set is part of the
+                /// builder's type.
+            },
+            false => quote! {
+                /// Names of the fields that have not been set, whether or not
+                /// build() requires them.
+            },
+        };
+        quote! {
+            #doc
+            pub fn unset_fields(&self) -> #alloc::vec::Vec<&'static str> {
+                #[allow(unused_mut)]
+                let mut unset = #alloc::vec::Vec::new();
+                #(#unset)*
+                unset
+            }
+        }
+    });
+
+    quote! {
+        #fields_header {
+            /// Every field that can be set on this builder, in declaration
+            /// order.
+            pub const FIELDS: &'static [#krate::BuilderFieldInfo] = &[#(#infos),*];
+        }
+
+        #queries_header {
+            #unset_fields
+
+            #(#is_set)*
+        }
+    }
+}
+
+// Name of the built type as written in docs, e.g. `Command::Start`.
+fn target_name(builder: &Builder) -> String {
+    match builder.variant {
+        Some(variant) => format!("{}::{}", builder.ast.ident, variant),
+        None => builder.ast.ident.to_string(),
+    }
+}
+
+fn generate_builder_doc(builder: &Builder) -> proc_macro2::TokenStream {
+    match &builder.options.doc {
+        Some(doc) => quote! { #[doc = #doc] },
+        None => {
+            let doc = format!("Builder for [`{}`].", target_name(builder));
+            quote! { #[doc = #doc] }
+        }
+    }
+}
+
+// Docs for build() listing which fields must be set and which fall back to a
+// default.
+fn generate_build_doc(builder: &Builder, outcome: &str) -> proc_macro2::TokenStream {
+    let list = |names: Vec<String>| {
+        names
+            .iter()
+            .map(|name| format!("`{}`", name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let fields = || builder.fields.iter().filter(|field| field.is_stored());
+    let required: Vec<_> = fields()
+        .filter(|field| field.is_required())
+        .map(|field| field.name.unraw().to_string())
+        .collect();
+    let defaulted: Vec<_> = fields()
+        .filter(|field| field.is_defaulted())
+        .map(|field| field.name.unraw().to_string())
+        .collect();
+
+    let mut doc = format!(
+        "Builds a [`{}`] from the values set on this builder.",
+        target_name(builder)
+    );
+    if !required.is_empty() {
+        doc += &format!("\n\nRequired fields: {}.", list(required));
+    }
+    if !defaulted.is_empty() {
+        doc += &format!("\n\nFields with defaults: {}.", list(defaulted));
+    }
+    doc += "\n\n";
+    doc += outcome;
+    quote! { #[doc = #doc] }
+}
+
+// The derives and `struct_attr` attributes requested for the builder. Immutable
+// builders always derive `Clone` since their setters clone the builder.
+fn generate_builder_attrs(options: &StructOptions, clone_impl: bool) -> proc_macro2::TokenStream {
+    let mut derives = options.derives.clone();
+    if clone_impl {
+        derives.retain(|path| {
+            path.segments
+                .last()
+                .is_none_or(|segment| segment.ident != "Clone")
+        });
+    }
+    let derive = (!derives.is_empty()).then(|| quote! { #[derive(#(#derives),*)] });
+    let attrs = &options.struct_attrs;
+    quote! {
+        #derive
+        #(#[#attrs])*
+    }
+}
+
+// `Clone` for the builder, which build() needs unless the builder is owned.
+// Unlike a derive it is bounded on the stored types, so that a builder of a
+// `Clone` type stays `Clone` whether or not its type parameters are. Bounds
+// that are not generic go through `for<'__b>` so that a builder with a field
+// that is not `Clone` still compiles, just without the impl applying. `set`
+// tells which fields are stored without the `Option` wrapper.
+fn generate_clone_impl(
+    builder: &Builder,
+    builder_name: &syn::Ident,
+    set: &[bool],
+) -> proc_macro2::TokenStream {
+    let Builder { ast, fields, .. } = builder;
+    let generics = &ast.generics;
+    let (impl_generics, ty_generics, _) = generics.split_for_impl();
+    let fields: Vec<_> = fields
+        .iter()
+        .zip(set)
+        .filter(|(field, _)| field.is_stored())
+        .collect();
+    let mut clone_generics = generics.clone();
+    clone_generics
+        .make_where_clause()
+        .predicates
+        .extend(fields.iter().map(|(field, &set)| -> syn::WherePredicate {
+            let ty = match set {
+                true => {
+                    let ty = &field.field.ty;
+                    quote! { #ty }
+                }
+                false => storage_type(field),
+            };
+            syn::parse_quote!(for<'__b> #ty: ::core::clone::Clone)
+        }));
+    let where_clause = &clone_generics.where_clause;
+    let clones = fields.iter().map(|(field, _)| {
+        let name = &field.name;
+        let cfgs = &field.cfgs;
+        quote! {
+            #(#cfgs)*
+            #name: ::core::clone::Clone::clone(&self.#name)
+        }
+    });
+    let phantom_init = generate_phantom_field(generics)
+        .map(|_| quote! { __phantom: ::core::marker::PhantomData, });
+
+    quote! {
+        impl #impl_generics ::core::clone::Clone for #builder_name #ty_generics #where_clause {
+            fn clone(&self) -> Self {
+                #builder_name {
+                    #(#clones,)*
+                    #phantom_init
+                }
+            }
+        }
+    }
+}
+
+// The value of a field in a new builder.
+fn generate_empty_value(field: &BuilderField) -> proc_macro2::TokenStream {
+    match &field.sub_builder {
+        Some(SubBuilder { support, .. }) => quote! { #support::__builder() },
+        None => quote! { ::core::option::Option::None },
+    }
+}
+
+fn generate_empty_field(field: &BuilderField) -> proc_macro2::TokenStream {
+    let name = &field.name;
+    let cfgs = &field.cfgs;
+    let value = generate_empty_value(field);
+    quote! {
+        #(#cfgs)*
+        #name: #value
+    }
+}
+
+// The builder that `builder_with()` returns for #[builder(required_ctor)],
+// e.g. `ConnectionBuilderWith`. It holds the required fields without an
+// `Option` and only has setters for the others, so build() cannot miss a
+// field and returns the value directly unless something is validated. If it
+// is, build() goes through the full builder and shares its error type.
+// Returns the builder and `builder_with()` itself.
+fn generate_builder_with(
+    builder: &Builder,
+    pattern: Pattern,
+) -> (proc_macro2::TokenStream, proc_macro2::TokenStream) {
+    let Builder {
+        ast,
+        options,
+        builder_name,
+        error_name,
+        constructor,
+        vis,
+        build_fn,
+        fields,
+        ..
+    } = builder;
+    let struct_name = &ast.ident;
+    let generics = &ast.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let with_name = format_ident!("{}With", builder_name);
+    let constructor_with = format_ident!("{}_with", constructor);
+    let set: Vec<_> = fields.iter().map(|field| field.ctor_arg).collect();
+    let stored: Vec<_> = fields.iter().filter(|field| field.is_stored()).collect();
+    let optional: Vec<_> = fields.iter().filter(|field| !field.ctor_arg).collect();
+
+    let builder_fields = stored.iter().map(|field| match field.ctor_arg {
+        true => {
+            let ty = &field.field.ty;
+            generate_builder_field_with_type(field, quote! { #ty })
+        }
+        false => generate_builder_field(field),
+    });
+    let setter_methods = optional
+        .iter()
+        .map(|field| generate_setter_method(field, pattern));
+    let accessors = generate_accessors(optional.iter().copied(), pattern, false);
+
+    let fallible = builder.has_validation();
+    let (build_output, build_body) = if fallible {
+        (
+            quote! { ::core::result::Result<#struct_name #ty_generics, #error_name> },
+            quote! {
+                ::core::convert::Into::<#builder_name #ty_generics>::into(self).__build()
+            },
+        )
+    } else {
+        let values = fields.iter().map(|field| {
+            let value = generate_build_value(field, &quote! { self });
+            match field.ctor_arg {
+                true => (value, false),
+                false => generate_field_value(builder, field, value),
+            }
+        });
+        (
+            quote! { #struct_name #ty_generics },
+            generate_build_body(builder, values, false),
+        )
+    };
+    let (build_receiver, build_value) = match pattern {
+        Pattern::Mutable => (
+            quote! { &mut self },
+            quote! { ::core::clone::Clone::clone(self).__build() },
+        ),
+        Pattern::Owned => (quote! { self }, quote! { self.__build() }),
+        Pattern::Immutable => (
+            quote! { &self },
+            quote! { ::core::clone::Clone::clone(self).__build() },
+        ),
+    };
+    let build_bounds = (pattern != Pattern::Owned && !generics.params.is_empty())
+        .then(|| quote! { where Self: ::core::clone::Clone });
+    let mut build_doc = format!(
+        "Builds a [`{}`] from the required fields given to [`{}::{}`] and the values set \
+         on this builder.",
+        target_name(builder),
+        struct_name,
+        constructor_with
+    );
+    if fallible {
+        build_doc += "\n\n# Errors\n\nReturns an error listing every check that fails.";
+    }
+
+    let builder_doc = format!(
+        "Builder for [`{}`] returned by [`{}::{}`], with the required fields already set.",
+        target_name(builder),
+        struct_name,
+        constructor_with
+    );
+    let builder_attrs = generate_builder_attrs(options, true);
+    let clone_impl = generate_clone_impl(builder, &with_name, &set);
+    let phantom_field = generate_phantom_field(generics);
+    let phantom_init = phantom_field
+        .as_ref()
+        .map(|_| quote! { __phantom: ::core::marker::PhantomData, });
+    let into_builder = stored.iter().map(|field| {
+        let name = &field.name;
+        let cfgs = &field.cfgs;
+        let value = match field.ctor_arg {
+            true => quote! { ::core::option::Option::Some(builder.#name) },
+            false => quote! { builder.#name },
+        };
+        quote! {
+            #(#cfgs)*
+            #name: #value
+        }
+    });
+    let with_builder = quote! {
+        #[doc = #builder_doc]
+        #builder_attrs
+        #vis struct #with_name #generics #where_clause {
+            #(#builder_fields,)*
+            #phantom_field
+        }
+
+        impl #impl_generics #with_name #ty_generics #where_clause {
+            #(#setter_methods)*
+
+            #accessors
+
+            #[doc = #build_doc]
+            pub fn #build_fn(#build_receiver) -> #build_output #build_bounds {
+                #build_value
+            }
+
+            #[doc(hidden)]
+            pub fn __build(self) -> #build_output {
+                #build_body
+            }
+        }
+
+        #clone_impl
+
+        impl #impl_generics ::core::convert::From<#with_name #ty_generics> for #builder_name #ty_generics #where_clause {
+            fn from(builder: #with_name #ty_generics) -> Self {
+                #builder_name {
+                    #(#into_builder,)*
+                    #phantom_init
+                }
+            }
+        }
+    };
+
+    let doc = format!(
+        "Creates a builder for [`{}`] from its required fields, in declaration order.",
+        target_name(builder)
+    );
+    let (params, args): (Vec<_>, Vec<_>) = fields
+        .iter()
+        .filter(|field| field.ctor_arg)
+        .map(|field| {
+            let name = &field.name;
+            let cfgs = &field.cfgs;
+            let (arg_ty, value) = generate_setter_arg(field, name, &field.field.ty);
+            (
+                quote! {
+                    #(#cfgs)*
+                    #name: #arg_ty
+                },
+                quote! {
+                    #(#cfgs)*
+                    let #name = #value;
+                },
+            )
+        })
+        .unzip();
+    let init = stored.iter().map(|field| match field.ctor_arg {
+        true => {
+            let name = &field.name;
+            let cfgs = &field.cfgs;
+            quote! {
+                #(#cfgs)*
+                #name
+            }
+        }
+        false => generate_empty_field(field),
+    });
+    let constructor = quote! {
+        #[doc = #doc]
+        #vis fn #constructor_with(#(#params),*) -> #with_name #ty_generics {
+            #(#args)*
+            #with_name {
+                #(#init,)*
+                #phantom_init
+            }
+        }
+    };
+    (with_builder, constructor)
+}
+
+// The generic arguments that name the given parameters, e.g. `'a, T, N` for
+// `<'a, T: Clone, const N: usize>`.
+fn generic_args(generics: &syn::Generics) -> Vec<proc_macro2::TokenStream> {
+    generics
+        .params
+        .iter()
+        .map(|param| match param {
+            syn::GenericParam::Lifetime(param) => {
+                let lifetime = &param.lifetime;
+                quote! { #lifetime }
+            }
+            syn::GenericParam::Type(param) => {
+                let ident = &param.ident;
+                quote! { #ident }
+            }
+            syn::GenericParam::Const(param) => {
+                let ident = &param.ident;
+                quote! { #ident }
+            }
+        })
+        .collect()
+}
+
+// A marker field that keeps every lifetime and type parameter of the struct in
+// use, for builders that do not store all fields with their original types.
+fn generate_phantom_field(generics: &syn::Generics) -> Option<proc_macro2::TokenStream> {
+    let lifetimes = generics.lifetimes().map(|param| {
+        let lifetime = &param.lifetime;
+        quote! { &#lifetime () }
+    });
+    let types = generics.type_params().map(|param| &param.ident);
+    let used: Vec<_> = lifetimes
+        .chain(types.map(|ident| quote! { #ident }))
+        .collect();
+    if used.is_empty() {
+        return None;
+    }
+    Some(quote! {
+        __phantom: ::core::marker::PhantomData<fn() -> (#(#used,)*)>
+    })
+}
+
+fn to_upper_camel_case(ident: &syn::Ident) -> String {
+    ident
+        .unraw()
+        .to_string()
+        .split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+fn to_snake_case(ident: &syn::Ident) -> String {
+    let mut snake = String::new();
+    for (i, ch) in ident.to_string().char_indices() {
+        if ch.is_uppercase() {
+            if i > 0 {
+                snake.push('_');
+            }
+            snake.extend(ch.to_lowercase());
+        } else {
+            snake.push(ch);
+        }
+    }
+    snake
+}
+
+// Everything needed to generate a single builder: one for a struct, or one
+// per variant when deriving on an enum.
+struct Builder<'a> {
+    ast: &'a DeriveInput,
+    options: &'a StructOptions,
+    // Path used to construct the value, e.g. `Command` or `Command::Start`.
+    target: proc_macro2::TokenStream,
+    variant: Option<&'a syn::Ident>,
+    builder_name: syn::Ident,
+    error_name: syn::Ident,
+    vis: syn::Visibility,
+    // Associated function on the input type that creates the builder.
+    constructor: syn::Ident,
+    build_fn: syn::Ident,
+    fields: Vec<BuilderField<'a>>,
+    // Indices of the `default_with` fields in the order build() computes
+    // them, so that each runs after the fields it reads.
+    default_order: Vec<usize>,
+}
+
+impl<'a> Builder<'a> {
+    // Whether build() can fail for reasons other than a missing required field.
+    fn has_validation(&self) -> bool {
+        self.options.build_fn.validate.is_some()
+            || self.fields.iter().any(|field| {
+                field.has_checks()
+                    || field.sub_builder.is_some()
+                    || field.options.default_with.is_some()
+            })
+    }
+
+    fn crate_path(&self) -> proc_macro2::TokenStream {
+        crate_path(self.options)
+    }
+
+    // The crate that allocating items are taken from, or `None` if nothing
+    // may allocate.
+    fn alloc_root(&self) -> Option<proc_macro2::TokenStream> {
+        match self.options.crate_root() {
+            CrateRoot::Std => Some(quote! { ::std }),
+            CrateRoot::Alloc => Some(quote! { ::alloc }),
+            CrateRoot::Core => None,
+        }
+    }
+
+    // A statement recording an error in build(). Errors are collected into
+    // `__errors` when there is an allocator, otherwise build() returns the
+    // first one.
+    fn report_error(&self, error: proc_macro2::TokenStream) -> proc_macro2::TokenStream {
+        match self.alloc_root() {
+            Some(_) => quote! { __errors.push(#error); },
+            None => quote! { return ::core::result::Result::Err(#error); },
+        }
+    }
+
+    fn new(
+        ast: &'a DeriveInput,
+        options: &'a StructOptions,
+        variant: Option<&'a syn::Ident>,
+        fields: &'a syn::Fields,
+    ) -> syn::Result<Self> {
+        let ident = &ast.ident;
+        let (target, builder_name, constructor) = match variant {
+            Some(variant) => (
+                quote! { #ident::#variant },
+                format!("{}{}Builder", ident, variant),
+                syn::Ident::new(&format!("build_{}", to_snake_case(variant)), variant.span()),
+            ),
+            None => (
+                quote! { #ident },
+                format!("{}Builder", ident),
+                syn::Ident::new("builder", ident.span()),
+            ),
+        };
+        let builder_name = match &options.name {
+            Some(name) => name.clone(),
+            None => syn::Ident::new(&builder_name, ident.span()),
+        };
+        let error_name = syn::Ident::new(&format!("{}Error", builder_name), ident.span());
+
+        let fields: syn::Result<Vec<_>> = fields
+            .iter()
+            .enumerate()
+            .map(|(index, field)| BuilderField::new(field, index, options))
+            .collect();
+        let fields = fields?;
+        let default_order = default_with_order(&fields)?;
+
+        Ok(Builder {
+            ast,
+            options,
+            target,
+            variant,
+            builder_name,
+            error_name,
+            vis: options
+                .vis
+                .clone()
+                .unwrap_or_else(|| syn::parse_quote!(pub)),
+            constructor,
+            build_fn: options
+                .build_fn
+                .name
+                .clone()
+                .unwrap_or_else(|| syn::Ident::new("build", ident.span())),
+            fields,
+            default_order,
+        })
+    }
+}
+
+// Orders the `default_with` fields so that each is computed after the others
+// it reads. A function reads a field if the field's name, `get_<field>` or
+// `is_<field>_set` appears in it; named functions cannot be looked into and
+// are computed in declaration order. A cycle is a compile error.
+fn default_with_order(fields: &[BuilderField]) -> syn::Result<Vec<usize>> {
+    fn idents(tokens: proc_macro2::TokenStream, found: &mut Vec<String>) {
+        for token in tokens {
+            match token {
+                proc_macro2::TokenTree::Ident(ident) => found.push(ident.unraw().to_string()),
+                proc_macro2::TokenTree::Group(group) => idents(group.stream(), found),
+                _ => {}
+            }
+        }
+    }
+
+    let computed: Vec<_> = fields
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| field.options.default_with.is_some())
+        .map(|(index, _)| index)
+        .collect();
+    let reads = |index: usize| -> Vec<usize> {
+        let mut found = Vec::new();
+        let default_with = &fields[index].options.default_with;
+        idents(quote! { #default_with }, &mut found);
+        computed
+            .iter()
+            .copied()
+            .filter(|&other| other != index)
+            .filter(|&other| {
+                let label = field_label(&fields[other]);
+                let names = [
+                    fields[other].name.unraw().to_string(),
+                    format!("get_{}", label),
+                    format!("is_{}_set", label),
+                ];
+                found.iter().any(|ident| names.contains(ident))
+            })
+            .collect()
+    };
+    let reads: Vec<_> = (0..fields.len()).map(reads).collect();
+
+    let mut order = Vec::new();
+    while order.len() < computed.len() {
+        let next = computed.iter().copied().find(|index| {
+            !order.contains(index) && reads[*index].iter().all(|read| order.contains(read))
+        });
+        match next {
+            Some(index) => order.push(index),
+            None => {
+                // Every remaining field reads another remaining one, so
+                // following those reads must come back around.
+                let mut path = vec![*computed
+                    .iter()
+                    .find(|index| !order.contains(*index))
+                    .unwrap()];
+                loop {
+                    let last = *path.last().unwrap();
+                    let read = reads[last]
+                        .iter()
+                        .copied()
+                        .find(|read| !order.contains(read))
+                        .unwrap();
+                    if let Some(start) = path.iter().position(|&index| index == read) {
+                        path.drain(..start);
+                        path.push(read);
+                        break;
+                    }
+                    path.push(read);
+                }
+                let names: Vec<_> = path
+                    .iter()
+                    .map(|&index| format!("`{}`", fields[index].name.unraw()))
+                    .collect();
+                return Err(syn::Error::new_spanned(
+                    &fields[path[0]].options.default_with,
+                    format!(
+                        "`default_with` functions read each other in a cycle: {}",
+                        names.join(" -> ")
+                    ),
+                ));
+            }
+        }
+    }
+    Ok(order)
+}
+
+// `From<Foo> for FooBuilder` and `Foo::to_builder()`, which start a builder
+// from an existing value. Only structs get them since a variant builder could
+// not accept the other variants. `set` tells which fields are stored without
+// the `Option` wrapper, as required fields of a typestate builder are.
+fn generate_conversions(
+    builder: &Builder,
+    builder_ty: proc_macro2::TokenStream,
+    set: &[bool],
+    phantom_init: Option<&proc_macro2::TokenStream>,
+) -> Option<proc_macro2::TokenStream> {
+    if builder.variant.is_some() {
+        return None;
+    }
+    let Builder {
+        ast,
+        builder_name,
+        vis,
+        fields,
+        ..
+    } = builder;
+    let struct_name = &ast.ident;
+    let (impl_generics, ty_generics, where_clause) = ast.generics.split_for_impl();
+
+    let init = |value: &dyn Fn(&syn::Member) -> proc_macro2::TokenStream| {
+        let fields = fields
+            .iter()
+            .zip(set)
+            .filter(|(field, _)| field.is_stored())
+            .map(|(field, &set)| {
+                let name = &field.name;
+                let cfgs = &field.cfgs;
+                let value = value(&field.member);
+                let value = if let Some(SubBuilder { support, .. }) = &field.sub_builder {
+                    quote! { #support::__to_builder(#value) }
+                } else if set || is_option_type(field.field) {
+                    value
+                } else {
+                    quote! { ::core::option::Option::Some(#value) }
+                };
+                quote! {
+                    #(#cfgs)*
+                    #name: #value
+                }
+            });
+        quote! {
+            #builder_name {
+                #(#fields,)*
+                #phantom_init
+            }
+        }
+    };
+    let from_value = init(&|member| quote! { value.#member });
+    let from_clone = init(&|member| quote! { ::core::clone::Clone::clone(&self.#member) });
+    // The higher-ranked bounds are only checked where to_builder() is used,
+    // so types with fields that are not Clone still derive fine.
+    let clone_bounds = fields
+        .iter()
+        .filter(|field| field.is_stored())
+        .map(|field| {
+            let ty = &field.field.ty;
+            quote! { for<'__b> #ty: ::core::clone::Clone }
+        });
+
+    Some(quote! {
+        impl #impl_generics ::core::convert::From<#struct_name #ty_generics> for #builder_ty #where_clause {
+            fn from(value: #struct_name #ty_generics) -> Self {
+                #from_value
+            }
+        }
+
+        impl #impl_generics #struct_name #ty_generics #where_clause {
+            /// Creates a builder with every field set from a clone of this
+            /// value.
+            #vis fn to_builder(&self) -> #builder_ty
+            where
+                #(#clone_bounds,)*
+            {
+                #from_clone
+            }
+        }
+    })
+}
+
+// In typestate mode every required field gets its own type parameter on the
+// builder. The parameter is `()` until the field is set and the field type
+// afterwards, so `build()` only exists once all of them hold a value.
+fn derive_typestate(builder: &Builder) -> proc_macro2::TokenStream {
+    let Builder {
+        ast,
+        builder_name,
+        error_name,
+        constructor,
+        vis,
+        build_fn,
+        fields,
+        ..
+    } = builder;
+    let struct_name = &ast.ident;
+    let generics = &ast.generics;
+
+    let states: Vec<_> = fields
+        .iter()
+        .map(|field| {
+            field.is_required().then(|| {
+                let param = format!("__{}", to_upper_camel_case(&field.name));
+                syn::Ident::new(&param, field.name.span())
+            })
+        })
+        .collect();
+    let params: Vec<_> = states.iter().flatten().collect();
+    let struct_args = generic_args(generics);
+    let unset_params: Vec<_> = params.iter().map(|_| quote! { () }).collect();
+    let set_params = fields
+        .iter()
+        .zip(&states)
+        .filter(|(_, state)| state.is_some())
+        .map(|(field, _)| &field.field.ty)
+        .collect::<Vec<_>>();
+
+    let mut builder_generics = generics.clone();
+    builder_generics
+        .params
+        .extend(params.iter().map(|param| -> syn::GenericParam {
+            syn::parse_quote! { #param = () }
+        }));
+    let mut state_generics = generics.clone();
+    state_generics
+        .params
+        .extend(params.iter().map(|param| -> syn::GenericParam {
+            syn::parse_quote! { #param }
+        }));
+    let (state_impl_generics, _, _) = state_generics.split_for_impl();
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let phantom_field = generate_phantom_field(generics);
+    let phantom_init = phantom_field
+        .as_ref()
+        .map(|_| quote! { __phantom: ::core::marker::PhantomData, });
+    let phantom_move = phantom_field
+        .as_ref()
+        .map(|_| quote! { __phantom: self.__phantom, });
+    let builder_fields = fields
+        .iter()
+        .zip(&states)
+        .filter(|(field, _)| field.is_stored())
+        .map(|(field, state)| match state {
+            Some(param) => generate_builder_field_with_type(field, quote! { #param }),
+            None => generate_builder_field(field),
+        });
+
+    let mut setter_methods = Vec::new();
+    for (index, field) in fields.iter().enumerate() {
+        let name = &field.name;
+        let ty = &field.field.ty;
+
+        if states[index].is_some() {
+            let setter_name = &field.setter_name;
+            let next_params = states.iter().enumerate().filter_map(|(i, state)| {
+                let param = state.as_ref()?;
+                Some(if i == index {
+                    quote! { #ty }
+                } else {
+                    quote! { #param }
+                })
+            });
+            let moved_fields = fields
+                .iter()
+                .enumerate()
+                .filter(|(_, field)| field.is_stored())
+                .map(|(i, field)| {
+                    let field_name = &field.name;
+                    let cfgs = &field.cfgs;
+                    if i == index {
+                        quote! { #field_name }
+                    } else {
+                        quote! { #(#cfgs)* #field_name: self.#field_name }
+                    }
+                });
+            let (arg_ty, value) = generate_setter_arg(field, name, ty);
+            let next_builder = quote! { #builder_name<#(#struct_args,)* #(#next_params),*> };
+            let vis = &field.setter_vis;
+            let attrs = field.method_attrs();
+            setter_methods.push(quote! {
+                #attrs
+                #vis fn #setter_name(self, #name: #arg_ty) -> #next_builder {
+                    let #name = #value;
+                    #builder_name {
+                        #(#moved_fields,)*
+                        #phantom_move
+                    }
+                }
+            });
+            if field.try_setter {
+                setter_methods.push(generate_try_setter(field, quote! { self }, next_builder));
+            }
+        } else {
+            setter_methods.push(generate_setter_method(field, Pattern::Owned));
+        }
+    }
+
+    let build_values = fields.iter().zip(&states).map(|(field, state)| {
+        let name = &field.name;
+        if state.is_some() {
+            (quote! { self.#name }, false)
+        } else {
+            let value = generate_build_value(field, &quote! { self });
+            generate_field_value(builder, field, value)
+        }
+    });
+    // Without validation there is nothing left that could fail once all
+    // required fields are set.
+    let fallible = builder.has_validation();
+    let build_body = generate_build_body(builder, build_values, fallible);
+    let (build_output, error_type) = if fallible {
+        (
+            quote! { ::core::result::Result<#struct_name #ty_generics, #error_name> },
+            Some(generate_error_type(builder, false)),
+        )
+    } else {
+        (quote! { #struct_name #ty_generics }, None)
+    };
+    let glob_match = fields
+        .iter()
+        .any(|field| field.options.matches.is_some())
+        .then(generate_glob_match);
+    let empty_fields = fields
+        .iter()
+        .zip(&states)
+        .filter(|(field, _)| field.is_stored())
+        .map(|(field, state)| match state {
+            Some(_) => {
+                let name = &field.name;
+                quote! { #name: () }
+            }
+            None => generate_empty_field(field),
+        });
+
+    let set: Vec<_> = states.iter().map(Option::is_some).collect();
+    let conversions = generate_conversions(
+        builder,
+        quote! { #builder_name<#(#struct_args,)* #(#set_params),*> },
+        &set,
+        phantom_init.as_ref(),
+    );
+
+    let builder_attrs = generate_builder_attrs(builder.options, false);
+    let builder_doc = generate_builder_doc(builder);
+    let introspection = generate_introspection(
+        builder,
+        quote! { impl #impl_generics #builder_name<#(#struct_args,)* #(#unset_params),*> #where_clause },
+        quote! { impl #state_impl_generics #builder_name<#(#struct_args,)* #(#params),*> #where_clause },
+    );
+    let build_doc = generate_build_doc(builder, "Only available once every required field is set.");
+    let constructor_doc = format!("Creates a builder for [`{}`].", target_name(builder));
+
+    quote! {
+        #builder_doc
+        #builder_attrs
+        #vis struct #builder_name #builder_generics #where_clause {
+            #(#builder_fields,)*
+            #phantom_field
+        }
+
+        impl #state_impl_generics #builder_name<#(#struct_args,)* #(#params),*> #where_clause {
+            #(#setter_methods)*
+
+            #glob_match
+        }
+
+        impl #impl_generics #builder_name<#(#struct_args,)* #(#set_params),*> #where_clause {
+            #build_doc
+            pub fn #build_fn(self) -> #build_output {
+                #build_body
+            }
+        }
+
+        #error_type
+
+        impl #impl_generics #struct_name #ty_generics #where_clause {
+            #[doc = #constructor_doc]
+            #vis fn #constructor() -> #builder_name<#(#struct_args,)* #(#unset_params),*> {
+                #builder_name {
+                    #(#empty_fields,)*
+                    #phantom_init
+                }
+            }
+        }
+
+        #conversions
+
+        #introspection
+    }
+}
+
+#[proc_macro_derive(Builder, attributes(builder))]
+pub fn derive(input: TokenStream) -> TokenStream {
+    let ast = parse_macro_input!(input as DeriveInput);
+
+    match derive_impl(ast) {
+        Ok(tokens) => tokens,
+        Err(err) => err.to_compile_error().into(),
+    }
+}
+
+fn derive_impl(ast: DeriveInput) -> syn::Result<TokenStream> {
+    let options = StructOptions::from_attrs(&ast.attrs)?;
+    // println!("{:#?}", ast);
+
+    let builders: syn::Result<Vec<_>> = match &ast.data {
+        syn::Data::Struct(data) => vec![Builder::new(&ast, &options, None, &data.fields)]
+            .into_iter()
+            .collect(),
+        syn::Data::Enum(_) if options.name.is_some() => {
+            return Err(syn::Error::new_spanned(
+                &ast.ident,
+                "`name` cannot be used on enums, whose builders are named after each variant",
+            ));
+        }
+        syn::Data::Enum(data) => data
+            .variants
+            .iter()
+            .map(|variant| Builder::new(&ast, &options, Some(&variant.ident), &variant.fields))
+            .collect(),
+        syn::Data::Union(_) => {
+            return Err(syn::Error::new_spanned(
+                &ast,
+                "Builder can only be derived for structs and enums",
+            ));
+        }
+    };
+
+    let mut expanded = proc_macro2::TokenStream::new();
+    for builder in builders? {
+        expanded.extend(generate_builder(&builder)?);
+    }
+    Ok(expanded.into())
+}
+
+// In const mode the constructor, setters and build() are `const fn`, so a
+// builder can fill a `static` or `const`. build() panics on a missing field,
+// which is a compile error during const evaluation. Destructors cannot run in
+// const fns, so every field type must be `Copy`.
+fn derive_const(builder: &Builder) -> syn::Result<proc_macro2::TokenStream> {
+    let Builder {
+        ast,
+        options,
+        target,
+        builder_name,
+        constructor,
+        vis,
+        build_fn,
+        fields,
+        ..
+    } = builder;
+    let struct_name = &ast.ident;
+
+    for field in fields {
+        let unsupported = if field.each_item.is_some() {
+            Some("`each`")
+        } else if field.sub_builder.is_some() {
+            Some("`sub_builder`")
+        } else if field.setter_into || field.try_setter {
+            Some("`setter(into)` or `try_setter`")
+        } else if field.has_checks() {
+            Some("`range`, `non_empty` or `matches`")
+        } else if field.default.is_some()
+            && !matches!(field.options.default, Some(DefaultValue::Expr(_)))
+        {
+            Some("`Default`, use `default = <const expression>`")
+        } else {
+            None
+        };
+        if let Some(unsupported) = unsupported {
+            return Err(syn::Error::new_spanned(
+                field.field,
+                format!("const builders do not support {}", unsupported),
+            ));
+        }
+    }
+
+    let generics = &ast.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let mut const_generics = generics.clone();
+    const_generics
+        .make_where_clause()
+        .predicates
+        .extend(fields.iter().map(|field| -> syn::WherePredicate {
+            let ty = &field.field.ty;
+            syn::parse_quote_spanned!(ty.span()=> #ty: ::core::marker::Copy)
+        }));
+    let const_where_clause = &const_generics.where_clause;
+
+    let builder_fields = fields
+        .iter()
+        .filter(|field| field.is_stored())
+        .map(generate_builder_field);
+    let empty_fields = fields
+        .iter()
+        .filter(|field| field.is_stored())
+        .map(generate_empty_field);
+    let setter_methods = fields
+        .iter()
+        .filter(|field| field.is_stored())
+        .map(|field| {
+            let name = &field.name;
+            let setter_name = &field.setter_name;
+            let vis = &field.setter_vis;
+            let attrs = field.method_attrs();
+            let ty = setter_value_type(field);
+            let value = if !is_option_type(field.field) || field.strip_option {
+                quote! { ::core::option::Option::Some(#name) }
+            } else {
+                quote! { #name }
+            };
+            quote! {
+                #attrs
+                #vis const fn #setter_name(mut self, #name: #ty) -> Self {
+                    self.#name = #value;
+                    self
+                }
+            }
+        });
+    let accessors = generate_accessors(fields, Pattern::Owned, true);
+    let build_values = fields.iter().map(|field| {
+        let name = &field.name;
+        let member = &field.member;
+        let cfgs = &field.cfgs;
+        let value = match &field.default {
+            _ if field.skip => {
+                let default = &field.default;
+                quote! { #default }
+            }
+            Some(default) if is_option_type(field.field) => quote! {
+                match self.#name {
+                    ::core::option::Option::Some(#name) => ::core::option::Option::Some(#name),
+                    ::core::option::Option::None => #default,
+                }
+            },
+            None if is_option_type(field.field) => quote! { self.#name },
+            Some(default) => quote! {
+                match self.#name {
+                    ::core::option::Option::Some(#name) => #name,
+                    ::core::option::Option::None => #default,
+                }
+            },
+            None => {
+                let message = format!("field `{}` is not set", name.unraw());
+                quote! {
+                    match self.#name {
+                        ::core::option::Option::Some(#name) => #name,
+                        ::core::option::Option::None => ::core::panic!(#message),
+                    }
+                }
+            }
+        };
+        quote! {
+            #(#cfgs)*
+            #member: #value
+        }
+    });
+
+    let builder_doc = generate_builder_doc(builder);
+    let builder_attrs = generate_builder_attrs(options, false);
+    let impl_header = quote! { impl #impl_generics #builder_name #ty_generics #where_clause };
+    let introspection = generate_introspection(builder, impl_header.clone(), impl_header);
+    let build_doc = generate_build_doc(
+        builder,
+        "# Panics\n\nPanics if a required field is not set. In a `const` or `static` this \
+         is a compile error.",
+    );
+    let constructor_doc = format!("Creates a builder for [`{}`].", target_name(builder));
+    let phantom_field = generate_phantom_field(generics);
+    let phantom_init = phantom_field
+        .as_ref()
+        .map(|_| quote! { __phantom: ::core::marker::PhantomData, });
+    let conversions = generate_conversions(
+        builder,
+        quote! { #builder_name #ty_generics },
+        &vec![false; fields.len()],
+        phantom_init.as_ref(),
+    );
+
+    Ok(quote! {
+        #builder_doc
+        #builder_attrs
+        #vis struct #builder_name #generics #where_clause {
+            #(#builder_fields,)*
+            #phantom_field
+        }
+
+        impl #impl_generics #builder_name #ty_generics #const_where_clause {
+            #(#setter_methods)*
+
+            #accessors
+
+            #build_doc
+            pub const fn #build_fn(self) -> #struct_name #ty_generics {
+                #target {
+                    #(#build_values,)*
+                }
+            }
+        }
+
+        impl #impl_generics #struct_name #ty_generics #where_clause {
+            #[doc = #constructor_doc]
+            #vis const fn #constructor() -> #builder_name #ty_generics {
+                #builder_name {
+                    #(#empty_fields,)*
+                    #phantom_init
+                }
+            }
+        }
+
+        #conversions
+
+        #introspection
+    })
+}
+
+// The methods a field adds to the builder under names the user picks, along
+// with the option that renames them.
+fn setter_names(field: &BuilderField) -> Vec<(String, &'static str)> {
+    if field.skip {
+        return Vec::new();
+    }
+    let setter_name = field.setter_name.unraw().to_string();
+    if let (Some(each_method), Some(_)) = (&field.options.each, &field.each_item) {
+        return vec![
+            (each_method.unraw().to_string(), "each = \"...\""),
+            (format!("extend_{}", setter_name), "setter(name = \"...\")"),
+        ];
+    }
+    let mut names = vec![(setter_name.clone(), "setter(name = \"...\")")];
+    if field.try_setter && field.sub_builder.is_none() {
+        names.push((format!("try_{}", setter_name), "setter(name = \"...\")"));
+    }
+    names
+}
+
+// The methods the builder generates on its own besides the setters, with how
+// to refer to them in errors: merge(), the accessors and the queries from
+// generate_introspection().
+fn generated_methods(builder: &Builder) -> Vec<(String, String)> {
+    let options = builder.options;
+    let mut methods = Vec::new();
+    let mut add = |name: String, method: String| methods.push((name, method));
+    if !options.typestate && !options.constant {
+        add("merge".to_owned(), "the builder's `merge()`".to_owned());
+    }
+    if !options.typestate {
+        add("reset".to_owned(), "the builder's `reset()`".to_owned());
+    }
+    if builder.alloc_root().is_some() {
+        add(
+            "unset_fields".to_owned(),
+            "the builder's `unset_fields()`".to_owned(),
+        );
+    }
+    for field in builder.fields.iter().filter(|field| field.is_stored()) {
+        let label = field_label(field);
+        let name = field.name.unraw();
+        if !options.typestate {
+            add(
+                format!("get_{}", label),
+                format!("the getter of `{}`", name),
+            );
+            add(
+                format!("clear_{}", label),
+                format!("`clear_{}()` of `{}`", label, name),
+            );
+        }
+        if field.sub_builder.is_none() && !(options.typestate && field.is_required()) {
+            add(
+                format!("is_{}_set", label),
+                format!("`is_{}_set()` of `{}`", label, name),
+            );
+        }
+    }
+    methods
+}
+
+// Reports a setter that has the name of a method the builder generates on
+// its own, `generated` listing those names with how to refer to them, rather
+// than leaving rustc to point a duplicate definition at the derive.
+fn check_method_names(builder: &Builder, generated: &[(String, String)]) -> syn::Result<()> {
+    for field in &builder.fields {
+        for (name, rename) in setter_names(field) {
+            if let Some((_, method)) = generated.iter().find(|(generated, _)| *generated == name) {
+                return Err(syn::Error::new_spanned(
+                    field.field,
+                    format!(
+                        "the setter `{}` has the same name as {}; rename it with `{}`",
+                        name, method, rename
+                    ),
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+// The path of the derive_builder crate, for the items that all builders
+// share. It only needs to be given if the dependency is renamed.
+fn crate_path(options: &StructOptions) -> proc_macro2::TokenStream {
+    match &options.krate {
+        Some(path) => quote! { #path },
+        None => quote! { ::derive_builder },
+    }
+}
+
+fn generate_builder(builder: &Builder) -> syn::Result<proc_macro2::TokenStream> {
+    let Builder {
+        ast,
+        options,
+        builder_name,
+        error_name,
+        constructor,
+        vis,
+        build_fn,
+        fields,
+        ..
+    } = builder;
+    let struct_name = &ast.ident;
+
+    let pattern = options.pattern();
+    if (options.env.is_some() || options.args) && options.crate_root() != CrateRoot::Std {
+        return Err(syn::Error::new_spanned(
+            struct_name,
+            "builders created from the environment or arguments need `crate_root = std`",
+        ));
+    }
+    if options.required_ctor && (options.typestate || options.constant) {
+        return Err(syn::Error::new_spanned(
+            struct_name,
+            "`required_ctor` is not supported by typestate or const builders",
+        ));
+    }
+    let has_default_with = !builder.default_order.is_empty();
+    if let Some(error) = &options.build_fn.error {
+        if options.build_fn.validate.is_none() && !has_default_with {
+            return Err(syn::Error::new_spanned(
+                error,
+                "`build_fn(error = ..)` is the error type of `validate` and `default_with` \
+                 functions and needs one of them",
+            ));
+        }
+    }
+    if has_default_with && (options.typestate || options.constant) {
+        return Err(syn::Error::new_spanned(
+            struct_name,
+            "`default_with` is not supported by typestate or const builders",
+        ));
+    }
+    check_method_names(builder, &generated_methods(builder))?;
+    if options.typestate {
+        if pattern != Pattern::Owned {
+            return Err(syn::Error::new_spanned(
+                struct_name,
+                "typestate builders only support the owned pattern",
+            ));
+        }
+        if options.env.is_some() || options.args {
+            return Err(syn::Error::new_spanned(
+                struct_name,
+                "typestate builders cannot be created from the environment or arguments",
+            ));
+        }
+        if let Some(field) = fields
+            .iter()
+            .find(|field| field.is_required() && !field.cfgs.is_empty())
+        {
+            return Err(syn::Error::new_spanned(
+                &field.cfgs[0],
+                "typestate builders do not support `#[cfg]` on required fields",
+            ));
+        }
+        return Ok(derive_typestate(builder));
+    }
+    if options.constant {
+        if pattern != Pattern::Owned {
+            return Err(syn::Error::new_spanned(
+                struct_name,
+                "const builders only support the owned pattern",
+            ));
+        }
+        if options.typestate
+            || options.env.is_some()
+            || options.args
+            || options.build_fn.validate.is_some()
+        {
+            return Err(syn::Error::new_spanned(
+                struct_name,
+                "const builders cannot be combined with `typestate`, `env`, `args` or `validate`",
+            ));
+        }
+        return derive_const(builder);
+    }
+
+    // Generate code sections
+    let builder_fields = fields
+        .iter()
+        .filter(|field| field.is_stored())
+        .map(generate_builder_field);
+    let setter_methods = fields
+        .iter()
+        .map(|field| generate_setter_method(field, pattern));
+    let accessors = generate_accessors(fields, pattern, false);
+    // `default_with` functions run on the builder moved into `__resolved`,
+    // which the computed values are stored into.
+    let receiver = match has_default_with {
+        true => quote! { __resolved },
+        false => quote! { self },
+    };
+    let build_values = fields.iter().map(|field| {
+        let value = generate_build_value(field, &receiver);
+        generate_field_value(builder, field, value)
+    });
+    let build_body = generate_build_body(builder, build_values, true);
+    let glob_match = fields
+        .iter()
+        .any(|field| field.options.matches.is_some())
+        .then(generate_glob_match);
+    let generics = &ast.generics;
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let build_output = quote! { ::core::result::Result<#struct_name #ty_generics, #error_name> };
+    let error_type = generate_error_type(builder, true);
+    // build() takes the builder apart in `__build`, after cloning it unless
+    // the builder is owned.
+    let (build_receiver, build_value) = match pattern {
+        Pattern::Mutable => (
+            quote! { &mut self },
+            quote! { ::core::clone::Clone::clone(self).__build() },
+        ),
+        Pattern::Owned => (quote! { self }, quote! { self.__build() }),
+        Pattern::Immutable => (
+            quote! { &self },
+            quote! { ::core::clone::Clone::clone(self).__build() },
+        ),
+    };
+    let build_bounds = (pattern != Pattern::Owned && !generics.params.is_empty())
+        .then(|| quote! { where Self: ::core::clone::Clone });
+    let builder_attrs = generate_builder_attrs(options, true);
+    let clone_impl = generate_clone_impl(builder, builder_name, &vec![false; fields.len()]);
+    let builder_doc = generate_builder_doc(builder);
+    let impl_header = quote! { impl #impl_generics #builder_name #ty_generics #where_clause };
+    let introspection = generate_introspection(builder, impl_header.clone(), impl_header);
+    let build_doc = generate_build_doc(
+        builder,
+        "# Errors\n\nReturns an error listing every required field that is not set and every \
+         check that fails.",
+    );
+    let constructor_doc = format!("Creates a builder for [`{}`].", target_name(builder));
+    let (builder_with, constructor_with) = options
+        .required_ctor
+        .then(|| generate_builder_with(builder, pattern))
+        .unzip();
+    let empty_fields = fields
+        .iter()
+        .filter(|field| field.is_stored())
+        .map(generate_empty_field);
+    let env_constructors = generate_env_constructors(builder);
+    let args_constructor = generate_args_constructor(builder, pattern);
+    let parse_fn = generate_parse_fn(builder);
+    let merge_fields = fields
+        .iter()
+        .filter(|field| field.is_stored())
+        .map(generate_merge_field);
+    let merge_name = syn::Ident::new("merge", proc_macro2::Span::call_site());
+    let merge = generate_setter(
+        pattern,
+        &quote! {
+            /// Overwrites the fields of this builder with every field that is
+            /// set in `other`.
+        },
+        &syn::parse_quote!(pub),
+        &merge_name,
+        quote! { other: Self },
+        |builder| {
+            quote! { #builder.__merge(other); }
+        },
+    );
+    let mut merge_generics = generics.clone();
+    merge_generics.make_where_clause().predicates.extend(
+        merge_bounds(fields).map(|bound| -> syn::WherePredicate { syn::parse_quote!(#bound) }),
+    );
+    let merge_where_clause = &merge_generics.where_clause;
+    let phantom_field = generate_phantom_field(generics);
+    let phantom_init = phantom_field
+        .as_ref()
+        .map(|_| quote! { __phantom: ::core::marker::PhantomData, });
+    let conversions = generate_conversions(
+        builder,
+        quote! { #builder_name #ty_generics },
+        &vec![false; fields.len()],
+        phantom_init.as_ref(),
+    );
+    // Lets other builders nest this one with `sub_builder`. Enum variants
+    // cannot be field types, so only structs get it.
+    let sub_builder_impl = builder.variant.is_none().then(|| {
+        let krate = builder.crate_path();
+        quote! {
+            impl #impl_generics #krate::__private::SubBuilder for #struct_name #ty_generics #merge_where_clause {
+                type Builder = #builder_name #ty_generics;
+                type Error = #error_name;
+
+                fn __builder() -> Self::Builder {
+                    Self::#constructor()
+                }
+
+                fn __build(builder: Self::Builder) -> #build_output {
+                    builder.__build()
+                }
+
+                fn __merge(builder: &mut Self::Builder, other: Self::Builder) {
+                    builder.__merge(other);
+                }
+
+                fn __to_builder(value: Self) -> Self::Builder {
+                    ::core::convert::From::from(value)
+                }
+
+                fn __fmt_error(
+                    error: &Self::Error,
+                    f: &mut ::core::fmt::Formatter<'_>,
+                    parent: &dyn ::core::ops::Fn(&mut ::core::fmt::Formatter<'_>) -> ::core::fmt::Result,
+                ) -> ::core::fmt::Result {
+                    error.__fmt_in(f, parent)
+                }
+            }
+        }
+    });
+
+    Ok(quote! {
+        #builder_doc
+        #builder_attrs
+        #vis struct #builder_name #generics #where_clause {
+            #(#builder_fields,)*
+            #phantom_field
+        }
+
+        impl #impl_generics #builder_name #ty_generics #where_clause {
+            #(#setter_methods)*
+
+            #accessors
+
+
+            #build_doc
+            pub fn #build_fn(#build_receiver) -> #build_output #build_bounds {
+                #build_value
+            }
+
+            #[doc(hidden)]
+            pub fn __build(self) -> #build_output {
+                #build_body
+            }
+
+            #glob_match
+
+            #env_constructors
+
+            #args_constructor
+
+            #parse_fn
+        }
+
+        impl #impl_generics #builder_name #ty_generics #merge_where_clause {
+            #merge
+
+            #[doc(hidden)]
+            pub fn __merge(&mut self, other: Self) {
+                #(#merge_fields)*
+            }
+        }
+
+        #clone_impl
+
+        #builder_with
+
+        #error_type
+
+        impl #impl_generics #struct_name #ty_generics #where_clause {
+            #[doc = #constructor_doc]
+            #vis fn #constructor() -> #builder_name #ty_generics {
+                #builder_name {
+                    #(#empty_fields,)*
+                    #phantom_init
+                }
+            }
+
+            #constructor_with
+        }
+
+        #conversions
+
+        #sub_builder_impl
+
+        #introspection
+    })
+}
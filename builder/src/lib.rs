@@ -1,196 +1,68 @@
-use proc_macro::TokenStream;
-use quote::quote;
-use syn::{parse2, parse_macro_input, spanned::Spanned, DeriveInput, Field, Type};
-
-fn extract_inner_type<'a>(field: &'a Field, ident_type: &str) -> Option<&'a Type> {
-    let path = match &field.ty {
-        Type::Path(type_path) => &type_path.path,
-        _ => return None,
-    };
-
-    let segment = path.segments.first()?;
-    if segment.ident != ident_type || path.segments.len() != 1 {
-        return None;
-    }
-
-    let args = match &segment.arguments {
-        syn::PathArguments::AngleBracketed(args) => args,
-        _ => return None,
-    };
-
-    match args.args.first()? {
-        syn::GenericArgument::Type(ty) => Some(ty),
-        _ => None,
-    }
-}
-
-fn is_option_type(field: &Field) -> bool {
-    extract_inner_type(field, "Option").is_some()
-}
-
-fn generate_builder_field(field: &Field) -> proc_macro2::TokenStream {
-    let name = &field.ident;
-    let ty = &field.ty;
-
-    if is_option_type(field) {
-        quote! { #name: #ty }
-    } else {
-        quote! { #name: ::std::option::Option<#ty> }
-    }
-}
-
-fn get_each_attribute_clean(field: &Field) -> syn::Result<Option<String>> {
-    for attr in &field.attrs {
-        let list = match &attr.meta {
-            syn::Meta::List(list) if list.path.is_ident("builder") => list,
-            _ => continue,
-        };
-        let each_attr: EachAttr = parse2(list.tokens.clone())?;
-        return Ok(Some(each_attr.value));
-    }
-    Ok(None)
-}
-
-struct EachAttr {
-    value: String,
+// Crates that have the "proc-macro" crate type can only export procedural
+// macros. The derive lives in derive_builder_impl so that this crate can also
+// export the types its expansions refer to, and re-exports it so that users
+// only import derive_builder.
+#![no_std]
+
+pub use derive_builder_impl::Builder;
+
+/// Describes a field of a builder, as listed in its `FIELDS` constant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuilderFieldInfo {
+    /// Name of the field.
+    pub name: &'static str,
+    /// Type of the field as written in the struct.
+    pub ty: &'static str,
+    /// How build() treats the field.
+    pub kind: BuilderFieldKind,
+    /// Doc comment of the field.
+    pub doc: &'static str,
 }
-impl syn::parse::Parse for EachAttr {
-    fn parse(input: syn::parse::ParseStream) -> syn::Result<Self> {
-        let ident: syn::Ident = input.parse()?;
-        if ident != "each" {
-            return Err(syn::Error::new_spanned(ident, "expected `each`"));
-        }
 
-        let _eq: syn::Token![=] = input.parse()?;
-        let value: syn::LitStr = input.parse()?;
-
-        Ok(EachAttr {
-            value: value.value(),
-        })
-    }
+/// How build() treats a field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuilderFieldKind {
+    /// Must be set before build().
+    Required,
+    /// An `Option` that stays `None` unless set.
+    Optional,
+    /// Falls back to a default value unless set.
+    Defaulted,
+    /// A collection filled one item at a time.
+    Each,
+    /// Built by a nested builder.
+    SubBuilder,
 }
 
-fn generate_setter_method(field: &Field) -> syn::Result<proc_macro2::TokenStream> {
-    let name = &field.ident;
+// Not public API. Used by the code the derive generates.
+#[doc(hidden)]
+pub mod __private {
+    use core::fmt;
 
-    if let Some(inner_ty) = extract_inner_type(&field, "Option") {
-        Ok(quote! {
-            pub fn #name(&mut self, #name: #inner_ty) -> &mut Self {
-                self.#name = Some(#name);
-                self
-            }
-        })
-    } else if let Some(each_attr) = get_each_attribute_clean(&field)? {
-        let each_method = syn::Ident::new(&each_attr, field.span());
-        let inner_type =
-            extract_inner_type(&field, "Vec").expect("fields with 'each' attribute must be Vec<T>");
+    /// Implemented for every struct with a builder that another builder can
+    /// nest with `#[builder(sub_builder)]`, so that the parent reaches it
+    /// whatever the builder is named.
+    #[diagnostic::on_unimplemented(
+        message = "`{Self}` does not have a builder that can be used as a `sub_builder`",
+        label = "not usable with `sub_builder`",
+        note = "`sub_builder` needs a struct that derives `Builder` without `typestate` or `const`"
+    )]
+    pub trait SubBuilder: Sized {
+        type Builder;
+        type Error;
 
-        Ok(quote! {
-            pub fn #each_method(&mut self, #each_method: #inner_type) -> &mut Self {
-                self.#name.get_or_insert_with(Vec::new).push(#each_method);
-                self
-            }
-        })
-    } else {
-        let ty = &field.ty;
-        Ok(quote! {
-            pub fn #name(&mut self, #name: #ty) -> &mut Self {
-                self.#name = Some(#name);
-                self
-            }
-        })
-    }
-}
+        fn __builder() -> Self::Builder;
 
-fn generate_build_field(field: &Field) -> syn::Result<proc_macro2::TokenStream> {
-    let name = &field.ident;
+        fn __build(builder: Self::Builder) -> Result<Self, Self::Error>;
 
-    if is_option_type(field) {
-        // Optional fields: use the Option value directly
-        Ok(quote! {
-            #name: self.#name.clone()
-        })
-    } else {
-        // Required fields: unwrap with error message
-        match get_each_attribute_clean(&field)? {
-            Some(_) => Ok(quote! {
-                #name: self.#name.clone().unwrap_or_default()
-            }),
-            _ => Ok(quote! {
-                #name: self.#name.clone()
-                    .ok_or_else(|| ::std::boxed::Box::<dyn ::std::error::Error>::from(
-                        ::std::format!("field `{}` is not set", ::std::stringify!(#name))
-                    ))?
-            }),
-        }
-    }
-}
-
-fn generate_empty_field(field: &Field) -> proc_macro2::TokenStream {
-    let name = &field.ident;
-    quote! { #name: ::std::option::Option::None }
-}
+        fn __merge(builder: &mut Self::Builder, other: Self::Builder);
 
-#[proc_macro_derive(Builder, attributes(builder))]
-pub fn derive(input: TokenStream) -> TokenStream {
-    let ast = parse_macro_input!(input as DeriveInput);
+        fn __to_builder(value: Self) -> Self::Builder;
 
-    match derive_impl(ast) {
-        Ok(tokens) => tokens,
-        Err(err) => err.to_compile_error().into(),
+        fn __fmt_error(
+            error: &Self::Error,
+            f: &mut fmt::Formatter<'_>,
+            parent: &dyn Fn(&mut fmt::Formatter<'_>) -> fmt::Result,
+        ) -> fmt::Result;
     }
 }
-
-fn derive_impl(ast: DeriveInput) -> syn::Result<TokenStream> {
-    let struct_name = &ast.ident;
-    let builder_name = syn::Ident::new(&format!("{}Builder", struct_name), struct_name.span());
-    // println!("{:#?}", ast);
-
-    // Extract named fields from struct
-    let fields = match &ast.data {
-        syn::Data::Struct(syn::DataStruct {
-            fields: syn::Fields::Named(syn::FieldsNamed { named, .. }),
-            ..
-        }) => named,
-        _ => {
-            return Err(syn::Error::new_spanned(
-                &ast,
-                "Builder can only be derived for structs with named fields",
-            ));
-        }
-    };
-
-    // Generate code sections
-    let builder_fields = fields.iter().map(generate_builder_field);
-    let setter_methods: syn::Result<Vec<_>> = fields.iter().map(generate_setter_method).collect();
-    let setter_methods = setter_methods?;
-    let build_fields: syn::Result<Vec<_>> = fields.iter().map(generate_build_field).collect();
-    let build_fields = build_fields?;
-    let empty_fields = fields.iter().map(generate_empty_field);
-
-    let expanded = quote! {
-        pub struct #builder_name {
-            #(#builder_fields,)*
-        }
-
-        impl #builder_name {
-            #(#setter_methods)*
-
-            pub fn build(&mut self) -> ::std::result::Result<#struct_name, ::std::boxed::Box<dyn ::std::error::Error>> {
-                ::std::result::Result::Ok(#struct_name {
-                    #(#build_fields,)*
-                })
-            }
-        }
-
-        impl #struct_name {
-            pub fn builder() -> #builder_name {
-                #builder_name {
-                    #(#empty_fields,)*
-                }
-            }
-        }
-    };
-
-    Ok(expanded.into())
-}
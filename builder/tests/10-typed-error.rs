@@ -0,0 +1,37 @@
+// The generated build method reports failures through a builder-specific error
+// enum rather than a boxed trait object, so callers can match on exactly which
+// field was left unset.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Command {
+    executable: String,
+    args: Vec<String>,
+    current_dir: Option<String>,
+}
+
+fn assert_error<E: std::error::Error + Clone + PartialEq>(_: &E) {}
+
+fn main() {
+    let err = Command::builder()
+        .args(vec!["build".to_owned()])
+        .build()
+        .err()
+        .unwrap();
+
+    assert_error(&err);
+    assert_eq!(
+        err,
+        CommandBuilderError::MissingField {
+            name: "executable"
+        }
+    );
+    assert_eq!(err.to_string(), "field `executable` is not set");
+
+    match Command::builder().executable("cargo".to_owned()).build() {
+        Err(CommandBuilderError::MissingField { name }) => assert_eq!(name, "args"),
+        Err(err) => panic!("unexpected error: {}", err),
+        Ok(_) => panic!("expected missing `args`"),
+    }
+}
@@ -0,0 +1,40 @@
+// With #[builder(typestate)] the builder tracks in its type which required
+// fields have been set. Setters consume and return the builder, and build()
+// is only available, and infallible, once every required field has a value.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    current_dir: Option<String>,
+    timeout: u64,
+}
+
+fn main() {
+    let command = Command::builder()
+        .arg("build".to_owned())
+        .timeout(30)
+        .executable("cargo".to_owned())
+        .arg("--release".to_owned())
+        .build();
+
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.args, vec!["build", "--release"]);
+    assert_eq!(command.current_dir, None);
+    assert_eq!(command.timeout, 30);
+
+    // Setting a field again replaces the earlier value.
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .timeout(1)
+        .current_dir("..".to_owned())
+        .executable("rustc".to_owned())
+        .build();
+
+    assert_eq!(command.executable, "rustc");
+    assert_eq!(command.current_dir.as_deref(), Some(".."));
+}
@@ -0,0 +1,15 @@
+// A typestate builder with an unset required field has no build() method, so
+// forgetting a field is a compile error instead of a runtime one.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Command {
+    executable: String,
+    timeout: u64,
+}
+
+fn main() {
+    let _command = Command::builder().executable("cargo".to_owned()).build();
+}
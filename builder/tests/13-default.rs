@@ -0,0 +1,88 @@
+// Fields marked #[builder(default)] fall back to Default::default() when they
+// are not set, and #[builder(default = ...)] evaluates the given expression
+// instead. Putting #[builder(default)] on the struct applies the Default
+// fallback to every field. Default expressions may call functions named like
+// other fields of the struct.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Command {
+    executable: String,
+    #[builder(default)]
+    args: Vec<String>,
+    #[builder(default = "/".to_owned())]
+    current_dir: String,
+    #[builder(default = Some(30))]
+    timeout: Option<u64>,
+}
+
+#[derive(Builder)]
+#[builder(default)]
+pub struct Limits {
+    files: u32,
+    #[builder(default = 4)]
+    threads: u32,
+    label: Option<String>,
+}
+
+#[derive(Builder)]
+pub struct Mirror {
+    host: String,
+    #[builder(default = host())]
+    fallback: String,
+    #[builder(default = fallback(2), range(max = fallback(3)))]
+    retries: u32,
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Replica {
+    host: String,
+    #[builder(default = host())]
+    fallback: String,
+}
+
+fn host() -> String {
+    "mirror.local".to_owned()
+}
+
+fn fallback(retries: u32) -> u32 {
+    retries * 2
+}
+
+fn main() {
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .build()
+        .unwrap();
+    assert!(command.args.is_empty());
+    assert_eq!(command.current_dir, "/");
+    assert_eq!(command.timeout, Some(30));
+
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .current_dir("..".to_owned())
+        .timeout(5)
+        .build()
+        .unwrap();
+    assert_eq!(command.current_dir, "..");
+    assert_eq!(command.timeout, Some(5));
+
+    let err = Command::builder().build().err().unwrap();
+    assert_eq!(err, CommandBuilderError::MissingField { name: "executable" });
+
+    let limits = Limits::builder().build().unwrap();
+    assert_eq!(limits.files, 0);
+    assert_eq!(limits.threads, 4);
+    assert_eq!(limits.label, None);
+
+    let mirror = Mirror::builder()
+        .host("primary.local".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(mirror.fallback, "mirror.local");
+    assert_eq!(mirror.retries, 4);
+    let replica = Replica::builder().host("primary.local".to_owned()).build();
+    assert_eq!(replica.fallback, "mirror.local");
+}
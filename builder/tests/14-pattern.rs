@@ -0,0 +1,47 @@
+// #[builder(pattern = "owned")] makes setters take and return the builder by
+// value and lets build() move the fields out, so field types do not need to
+// implement Clone. #[builder(pattern = "immutable")] keeps the builder intact
+// and returns an updated copy from every setter.
+
+use derive_builder::Builder;
+use std::fs::File;
+
+#[derive(Builder)]
+#[builder(pattern = "owned")]
+pub struct Job {
+    name: String,
+    callback: Box<dyn Fn(u32) -> u32>,
+    log: Option<File>,
+    #[builder(each = "step")]
+    steps: Vec<Box<dyn Fn()>>,
+}
+
+#[derive(Builder)]
+#[builder(pattern = "immutable")]
+pub struct Endpoint {
+    host: String,
+    port: u16,
+}
+
+fn main() {
+    let job = Job::builder()
+        .name("double".to_owned())
+        .callback(Box::new(|x| x * 2))
+        .step(Box::new(|| {}))
+        .build()
+        .unwrap();
+    assert_eq!(job.name, "double");
+    assert_eq!((job.callback)(21), 42);
+    assert!(job.log.is_none());
+    assert_eq!(job.steps.len(), 1);
+
+    let base = Endpoint::builder().host("localhost".to_owned());
+    let http = base.port(80);
+    let https = base.port(443);
+    assert_eq!(http.build().unwrap().port, 80);
+    assert_eq!(https.build().unwrap().port, 443);
+    assert_eq!(
+        base.build().err().unwrap(),
+        EndpointBuilderError::MissingField { name: "port" }
+    );
+}
@@ -0,0 +1,59 @@
+// Generic parameters, lifetimes, const generics and where-clauses on the input
+// struct carry over to the generated builder, its impl blocks and builder().
+
+use derive_builder::Builder;
+use std::fmt::Debug;
+
+pub trait Codec {
+    fn encode(&self, input: &str) -> String;
+}
+
+#[derive(Clone)]
+pub struct Upper;
+
+impl Codec for Upper {
+    fn encode(&self, input: &str) -> String {
+        input.to_uppercase()
+    }
+}
+
+#[derive(Builder)]
+pub struct Request<'a, T: Codec, const N: usize>
+where
+    T: Send,
+{
+    path: &'a str,
+    codec: T,
+    headers: [u8; N],
+    body: Option<&'a [u8]>,
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Pair<K, V = String>
+where
+    K: Debug,
+{
+    key: K,
+    value: V,
+    #[builder(default)]
+    weight: u32,
+}
+
+fn main() {
+    let path = String::from("/index");
+    let request = Request::builder()
+        .path(&path)
+        .codec(Upper)
+        .headers([1, 2, 3])
+        .build()
+        .unwrap();
+    assert_eq!(request.codec.encode(request.path), "/INDEX");
+    assert_eq!(request.headers.len(), 3);
+    assert!(request.body.is_none());
+
+    let pair: Pair<u8> = Pair::builder().value("one".to_owned()).key(1).build();
+    assert_eq!(pair.key, 1);
+    assert_eq!(pair.value, "one");
+    assert_eq!(pair.weight, 0);
+}
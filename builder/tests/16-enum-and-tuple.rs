@@ -0,0 +1,52 @@
+// Deriving Builder on an enum generates one builder per variant. The builder
+// for variant `Start` of enum `Command` is `CommandStartBuilder`, it is created
+// by `Command::build_start()` and its build() returns a `Command`.
+//
+// Positional fields, in tuple structs and tuple variants, get setters named
+// `_0`, `_1`, ... unless a name is given with #[builder(setter(name = "..."))].
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug, PartialEq)]
+pub enum Command {
+    Start {
+        program: String,
+        #[builder(each = "arg")]
+        args: Vec<String>,
+    },
+    Signal(u32, #[builder(setter(name = "reason"))] Option<String>),
+    StopAll,
+}
+
+#[derive(Builder)]
+pub struct Point(i32, #[builder(setter(name = "y"))] i32);
+
+fn main() {
+    let start = Command::build_start()
+        .program("cargo".to_owned())
+        .arg("test".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(
+        start,
+        Command::Start {
+            program: "cargo".to_owned(),
+            args: vec!["test".to_owned()],
+        }
+    );
+
+    let signal = Command::build_signal()._0(9).reason("kill".to_owned()).build();
+    assert_eq!(signal, Ok(Command::Signal(9, Some("kill".to_owned()))));
+
+    let err = Command::build_signal().build().unwrap_err();
+    assert_eq!(err, CommandSignalBuilderError::MissingField { name: "_0" });
+
+    let mut stop: CommandStopAllBuilder = Command::build_stop_all();
+    assert_eq!(stop.build(), Ok(Command::StopAll));
+
+    let point = Point::builder()._0(1).y(2).build().unwrap();
+    assert_eq!((point.0, point.1), (1, 2));
+
+    let err = Point::builder()._0(1).build().err().unwrap();
+    assert_eq!(err, PointBuilderError::MissingField { name: "y" });
+}
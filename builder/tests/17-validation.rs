@@ -0,0 +1,129 @@
+// #[builder(build_fn(validate = path))] runs a function on the builder before
+// build() constructs the value and reports its error as
+// `ValidationFailed`, holding the error itself with `error = Type` or its
+// message otherwise. Fields can also declare constraints that are checked on
+// the resolved value: `range(min = .., max = ..)`, `non_empty` and
+// `matches = "glob"`, each with its own error variant.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug)]
+#[builder(build_fn(validate = check_server))]
+pub struct Server {
+    #[builder(matches = "*.example.com")]
+    host: String,
+    #[builder(range(min = 1, max = 1024))]
+    port: u16,
+    #[builder(range(min = 1))]
+    workers: Option<u32>,
+    #[builder(each = "alias", non_empty)]
+    aliases: Vec<String>,
+}
+
+fn check_server(builder: &ServerBuilder) -> Result<(), String> {
+    match builder.workers {
+        Some(workers) if workers > 64 => Err(format!("{} workers is too many", workers)),
+        _ => Ok(()),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PoolError {
+    TooSmall { min: usize },
+}
+
+impl std::fmt::Display for PoolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            PoolError::TooSmall { min } => write!(f, "pool needs at least {} connections", min),
+        }
+    }
+}
+
+#[derive(Builder, Debug)]
+#[builder(build_fn(validate = check_pool, error = PoolError))]
+pub struct Pool {
+    size: usize,
+}
+
+fn check_pool(builder: &PoolBuilder) -> Result<(), PoolError> {
+    match builder.size {
+        Some(size) if size < 2 => Err(PoolError::TooSmall { min: 2 }),
+        _ => Ok(()),
+    }
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Retry {
+    #[builder(range(max = 10))]
+    attempts: u8,
+}
+
+fn main() {
+    let server = || {
+        let mut builder = Server::builder();
+        builder
+            .host("api.example.com".to_owned())
+            .port(443)
+            .alias("api".to_owned());
+        builder
+    };
+
+    let ok = server().build().unwrap();
+    assert_eq!(ok.host, "api.example.com");
+
+    let err = server().host("example.org".to_owned()).build().unwrap_err();
+    assert_eq!(
+        err,
+        ServerBuilderError::PatternMismatch {
+            field: "host",
+            pattern: "*.example.com"
+        }
+    );
+    assert_eq!(err.to_string(), "field `host` does not match `*.example.com`");
+
+    let err = server().port(0).build().unwrap_err();
+    assert_eq!(
+        err,
+        ServerBuilderError::OutOfRange {
+            field: "port",
+            range: "1..=1024"
+        }
+    );
+
+    let err = server().workers(0).build().unwrap_err();
+    assert_eq!(err.to_string(), "field `workers` is out of range `1..`");
+
+    let err = server().workers(100).build().unwrap_err();
+    assert_eq!(
+        err,
+        ServerBuilderError::ValidationFailed {
+            message: "100 workers is too many".to_owned()
+        }
+    );
+
+    let err = Server::builder()
+        .host("a.example.com".to_owned())
+        .port(80)
+        .build()
+        .unwrap_err();
+    assert_eq!(err, ServerBuilderError::Empty { field: "aliases" });
+
+    let err = Pool::builder().size(1).build().unwrap_err();
+    assert_eq!(
+        err,
+        PoolBuilderError::ValidationFailed(PoolError::TooSmall { min: 2 })
+    );
+    assert_eq!(err.to_string(), "pool needs at least 2 connections");
+    assert_eq!(Pool::builder().size(4).build().unwrap().size, 4);
+
+    assert!(Retry::builder().attempts(3).build().is_ok());
+    assert_eq!(
+        Retry::builder().attempts(11).build().err(),
+        Some(RetryBuilderError::OutOfRange {
+            field: "attempts",
+            range: "..=10"
+        })
+    );
+}
@@ -0,0 +1,61 @@
+// Setter conversions: #[builder(setter(into))] makes a setter accept anything
+// that converts into the field type, #[builder(setter(strip_option = false))]
+// makes an Option<T> setter take the Option itself, and #[builder(try_setter)]
+// adds a `try_` setter that goes through TryInto. All of them can be given on
+// the struct as defaults for every field and overridden per field.
+
+use derive_builder::Builder;
+use std::num::TryFromIntError;
+
+#[derive(Builder)]
+#[builder(setter(into), try_setter)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    #[builder(setter(into = false))]
+    nice: i8,
+    #[builder(setter(strip_option = false))]
+    current_dir: Option<String>,
+    #[builder(try_setter = false)]
+    label: Option<String>,
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Port {
+    #[builder(setter(into), try_setter)]
+    number: u16,
+}
+
+fn main() -> Result<(), TryFromIntError> {
+    let command = Command::builder()
+        .executable("cargo")
+        .arg("build")
+        .nice(5)
+        .current_dir(None::<String>)
+        .label("release")
+        .build()
+        .unwrap();
+    assert_eq!(command.executable, "cargo");
+    assert_eq!(command.args, vec!["build"]);
+    assert_eq!(command.nice, 5);
+    assert_eq!(command.current_dir, None);
+    assert_eq!(command.label.as_deref(), Some("release"));
+
+    let mut builder = Command::builder();
+    builder.executable("cargo").try_nice(-3i64)?;
+    assert!(builder.try_nice(300).is_err());
+    builder.try_current_dir("/tmp")?;
+    let command = builder.build().unwrap();
+    assert_eq!(command.nice, -3);
+    assert_eq!(command.current_dir.as_deref(), Some("/tmp"));
+
+    let port = Port::builder().number(8080u16).build();
+    assert_eq!(port.number, 8080);
+    let port = Port::builder().try_number(443u64)?.build();
+    assert_eq!(port.number, 443);
+    assert!(Port::builder().try_number(-1i32).is_err());
+
+    Ok(())
+}
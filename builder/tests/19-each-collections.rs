@@ -0,0 +1,89 @@
+// #[builder(each = "...")] works with any collection that implements Default
+// and Extend. Map fields take the key and the value as two arguments. Every
+// `each` field also gets an `extend_<field>` setter that adds all items of an
+// iterator at once. Only `HashMap`, `BTreeMap` and `IndexMap` are treated as
+// maps; other collections, whatever their name, add their first type argument.
+
+use derive_builder::Builder;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+
+// Stand-in for smallvec::SmallVec, whose parameter is an array type.
+#[derive(Default)]
+pub struct SmallVec<A: Array>(Vec<A::Item>);
+
+pub trait Array {
+    type Item;
+}
+
+impl<T, const N: usize> Array for [T; N] {
+    type Item = T;
+}
+
+impl<A: Array> Clone for SmallVec<A>
+where
+    A::Item: Clone,
+{
+    fn clone(&self) -> Self {
+        SmallVec(self.0.clone())
+    }
+}
+
+impl<A: Array> Extend<A::Item> for SmallVec<A> {
+    fn extend<I: IntoIterator<Item = A::Item>>(&mut self, iter: I) {
+        self.0.extend(iter);
+    }
+}
+
+// A collection of `T` despite its name.
+#[derive(Clone, Default)]
+pub struct TileMap<T>(Vec<T>);
+
+impl<T> Extend<T> for TileMap<T> {
+    fn extend<I: IntoIterator<Item = T>>(&mut self, iter: I) {
+        self.0.extend(iter);
+    }
+}
+
+#[derive(Builder)]
+pub struct Config {
+    #[builder(each = "tag")]
+    tags: HashSet<String>,
+    #[builder(each = "level")]
+    levels: BTreeSet<u8>,
+    #[builder(each = "job")]
+    queue: VecDeque<u32>,
+    #[builder(each = "env")]
+    env: HashMap<String, String>,
+    #[builder(each = "limit")]
+    limits: BTreeMap<&'static str, u64>,
+    #[builder(each = "port")]
+    ports: SmallVec<[u16; 4]>,
+    #[builder(each = "tile")]
+    tiles: TileMap<char>,
+}
+
+fn main() {
+    let config = Config::builder()
+        .tag("a".to_owned())
+        .tag("a".to_owned())
+        .extend_levels([3, 1, 2])
+        .job(7)
+        .job(8)
+        .env("HOME".to_owned(), "/root".to_owned())
+        .extend_env(vec![("USER".to_owned(), "root".to_owned())])
+        .limit("files", 1024)
+        .port(80)
+        .extend_ports([443, 8080])
+        .tile('#')
+        .build()
+        .unwrap();
+
+    assert_eq!(config.tags.len(), 1);
+    assert_eq!(config.levels.into_iter().collect::<Vec<_>>(), [1, 2, 3]);
+    assert_eq!(config.queue, [7, 8]);
+    assert_eq!(config.env["HOME"], "/root");
+    assert_eq!(config.env["USER"], "root");
+    assert_eq!(config.limits["files"], 1024);
+    assert_eq!(config.ports.0, [80, 443, 8080]);
+    assert_eq!(config.tiles.0, ['#']);
+}
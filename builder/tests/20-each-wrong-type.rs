@@ -0,0 +1,12 @@
+// A field marked with `each` must be a collection whose element type can be
+// read off its type, otherwise the derive reports an error on the field type.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Command {
+    #[builder(each = "arg")]
+    args: String,
+}
+
+fn main() {}
@@ -0,0 +1,69 @@
+// A field marked #[builder(sub_builder)] is stored as the builder of its own
+// type. The parent exposes it through `fn field(&mut self) -> &mut
+// FieldBuilder` so nested values can be filled in place, and build() builds
+// the child as well. Errors from the child are wrapped in a variant named
+// after the field and display the full path of the field that failed.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug, PartialEq)]
+pub struct Tls {
+    cert: String,
+    #[builder(default)]
+    verify: bool,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+pub struct Server {
+    host: String,
+    #[builder(default = 80)]
+    port: u16,
+    #[builder(sub_builder)]
+    tls: Tls,
+}
+
+#[derive(Builder, Debug)]
+pub struct Config {
+    name: String,
+    #[builder(sub_builder)]
+    server: Server,
+}
+
+fn main() {
+    let mut builder = Config::builder();
+    builder.name("prod".to_owned());
+    builder.server().host("example.com".to_owned()).port(443);
+    builder.server().tls().cert("cert.pem".to_owned());
+
+    let config = builder.build().unwrap();
+    assert_eq!(config.name, "prod");
+    assert_eq!(
+        config.server,
+        Server {
+            host: "example.com".to_owned(),
+            port: 443,
+            tls: Tls {
+                cert: "cert.pem".to_owned(),
+                verify: false,
+            },
+        }
+    );
+
+    let mut builder = Config::builder();
+    builder.name("dev".to_owned());
+    builder.server().host("localhost".to_owned());
+    let err = builder.build().unwrap_err();
+    assert_eq!(
+        err,
+        ConfigBuilderError::Server(ServerBuilderError::Tls(TlsBuilderError::MissingField {
+            name: "cert"
+        }))
+    );
+    assert_eq!(err.to_string(), "field `server.tls.cert` is not set");
+
+    let err = Config::builder().name("dev".to_owned()).build().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "field `server.host` is not set; field `server.tls.cert` is not set"
+    );
+}
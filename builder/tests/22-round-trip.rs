@@ -0,0 +1,67 @@
+// Every struct builder can be started from an existing value, either by
+// consuming it with `From<Command> for CommandBuilder` or by cloning its
+// fields with `Command::to_builder()`. The result can be modified and built
+// again, which makes it easy to derive a variation of a value.
+
+use derive_builder::Builder;
+use std::rc::Rc;
+
+#[derive(Builder, Debug, PartialEq)]
+pub struct Command {
+    executable: String,
+    #[builder(each = "arg")]
+    args: Vec<String>,
+    current_dir: Option<String>,
+    #[builder(default = 3)]
+    retries: u32,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(typestate)]
+pub struct Point {
+    x: i32,
+    y: i32,
+    label: Option<String>,
+}
+
+// Not Clone, so only the From impl is usable.
+#[derive(Builder)]
+pub struct Handle {
+    inner: std::cell::Cell<u8>,
+    shared: Rc<u8>,
+}
+
+fn main() {
+    let command = Command::builder()
+        .executable("cargo".to_owned())
+        .arg("build".to_owned())
+        .build()
+        .unwrap();
+
+    let release = command
+        .to_builder()
+        .arg("--release".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(release.args, vec!["build", "--release"]);
+    assert_eq!(release.retries, 3);
+
+    let mut builder = CommandBuilder::from(command);
+    builder.current_dir("/tmp".to_owned());
+    let moved = builder.build().unwrap();
+    assert_eq!(moved.executable, "cargo");
+    assert_eq!(moved.args, vec!["build"]);
+    assert_eq!(moved.current_dir.as_deref(), Some("/tmp"));
+
+    let point = Point::builder().x(1).y(2).build();
+    let shifted = point.to_builder().x(5).build();
+    assert_eq!(shifted, Point { x: 5, y: 2, label: None });
+    assert_eq!(PointBuilder::from(point).build().x, 1);
+
+    let handle = Handle {
+        inner: std::cell::Cell::new(7),
+        shared: Rc::new(1),
+    };
+    let handle = HandleBuilder::from(handle).build().unwrap();
+    assert_eq!(handle.inner.get(), 7);
+}
@@ -0,0 +1,87 @@
+// merge() layers one partial builder over another: every field that is set
+// in `other` overwrites the same field in `self`, and fields left unset keep
+// their current value. Collections with `each` are appended by default or
+// replaced with #[builder(merge = "replace")], and sub-builders are merged
+// field by field. A field named `merge` needs its setter renamed.
+
+use derive_builder::Builder;
+use std::collections::HashMap;
+
+#[derive(Builder, Debug, PartialEq)]
+pub struct Logging {
+    level: String,
+    #[builder(default)]
+    color: bool,
+}
+
+#[derive(Builder, Debug)]
+pub struct Config {
+    name: String,
+    #[builder(default = 8080)]
+    port: u16,
+    motd: Option<String>,
+    #[builder(each = "plugin")]
+    plugins: Vec<String>,
+    #[builder(each = "env", merge = "replace")]
+    env: HashMap<String, String>,
+    #[builder(sub_builder)]
+    logging: Logging,
+}
+
+#[derive(Builder, Debug)]
+#[builder(pattern = "owned")]
+pub struct Layer {
+    a: u8,
+    b: u8,
+}
+
+#[derive(Builder, Debug)]
+pub struct Conflict {
+    #[builder(setter(name = "set_merge"), default)]
+    merge: bool,
+}
+
+fn main() {
+    let mut defaults = Config::builder();
+    defaults
+        .name("app".to_owned())
+        .plugin("core".to_owned())
+        .env("MODE".to_owned(), "dev".to_owned());
+    defaults.logging().level("info".to_owned());
+
+    let mut file = Config::builder();
+    file.port(9000)
+        .plugin("metrics".to_owned())
+        .env("REGION".to_owned(), "eu".to_owned());
+    file.logging().color(true);
+
+    let mut cli = Config::builder();
+    cli.motd("hello".to_owned());
+
+    let config = defaults.merge(file).merge(cli).build().unwrap();
+    assert_eq!(config.name, "app");
+    assert_eq!(config.port, 9000);
+    assert_eq!(config.motd.as_deref(), Some("hello"));
+    assert_eq!(config.plugins, vec!["core", "metrics"]);
+    assert_eq!(config.env.len(), 1);
+    assert_eq!(config.env["REGION"], "eu");
+    assert_eq!(
+        config.logging,
+        Logging {
+            level: "info".to_owned(),
+            color: true,
+        }
+    );
+
+    let layer = Layer::builder()
+        .a(1)
+        .b(2)
+        .merge(Layer::builder().b(3))
+        .build()
+        .unwrap();
+    assert_eq!((layer.a, layer.b), (1, 3));
+
+    let mut conflict = Conflict::builder();
+    conflict.merge(Conflict::builder().set_merge(true).clone());
+    assert!(conflict.build().unwrap().merge);
+}
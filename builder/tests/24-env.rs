@@ -0,0 +1,78 @@
+// #[builder(env(prefix = "APP_"))] generates `from_env()` and
+// `from_env_map()`, which read each field from `APP_<FIELD>` and parse it with
+// `FromStr`. Collections with `each` are split on the separator, maps take
+// `KEY=VALUE` items. A variable that fails to parse is reported by name.
+
+use derive_builder::Builder;
+use std::collections::{BTreeMap, HashMap};
+use std::net::IpAddr;
+
+#[derive(Builder, Debug)]
+#[builder(env(prefix = "APP_", separator = ";"))]
+pub struct Settings {
+    host: IpAddr,
+    #[builder(default = 8080)]
+    port: u16,
+    log_level: Option<String>,
+    #[builder(each = "peer")]
+    peers: Vec<String>,
+    #[builder(each = "limit")]
+    limits: BTreeMap<String, u32>,
+    #[builder(env(name = "DATABASE_URL"))]
+    database: String,
+    #[builder(env(skip), default)]
+    verbose: bool,
+}
+
+fn vars(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect()
+}
+
+fn main() {
+    let env = vars(&[
+        ("APP_HOST", "127.0.0.1"),
+        ("APP_PEERS", "a.local; b.local;"),
+        ("APP_LIMITS", "cpu=2;memory=512"),
+        ("DATABASE_URL", "postgres://db"),
+        ("APP_VERBOSE", "not a bool"),
+    ]);
+    let settings = SettingsBuilder::from_env_map(&env)
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(settings.host, IpAddr::from([127, 0, 0, 1]));
+    assert_eq!(settings.port, 8080);
+    assert_eq!(settings.log_level, None);
+    assert_eq!(settings.peers, vec!["a.local", "b.local"]);
+    assert_eq!(settings.limits["memory"], 512);
+    assert_eq!(settings.database, "postgres://db");
+    assert!(!settings.verbose);
+
+    // Values from the environment are ordinary builder state and can still
+    // be overridden with setters.
+    let mut builder = SettingsBuilder::from_env_map(&env).unwrap();
+    let settings = builder.port(9000).build().unwrap();
+    assert_eq!(settings.port, 9000);
+
+    let err = SettingsBuilder::from_env_map(&vars(&[("APP_PORT", "eighty")])).err().unwrap();
+    assert_eq!(
+        err,
+        SettingsBuilderError::InvalidEnv {
+            var: "APP_PORT",
+            message: "invalid digit found in string".to_owned(),
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        "environment variable `APP_PORT` is invalid: invalid digit found in string"
+    );
+
+    let err = SettingsBuilder::from_env_map(&vars(&[("APP_LIMITS", "cpu")])).err().unwrap();
+    assert!(matches!(err, SettingsBuilderError::InvalidEnv { var: "APP_LIMITS", .. }));
+
+    let err = SettingsBuilder::from_env().unwrap().build().unwrap_err();
+    assert!(err.missing_fields().contains(&"host"));
+}
@@ -0,0 +1,106 @@
+// #[builder(args)] generates `from_args()`, which accepts `--flag=value` and
+// `--flag value` arguments and passes each value to the setter of the field.
+// Boolean fields are bare flags, `each` fields may be repeated, and `--help`
+// returns an error that displays `HELP`, built from the field doc comments.
+
+use derive_builder::Builder;
+use std::collections::HashMap;
+
+#[derive(Builder, Debug)]
+#[builder(args)]
+pub struct Tool {
+    /// Address to listen on.
+    listen_addr: String,
+    /// Number of worker threads.
+    #[builder(default = 4)]
+    workers: usize,
+    /// Print more output.
+    #[builder(default)]
+    verbose: bool,
+    /// Files to process.
+    #[builder(each = "input")]
+    inputs: Vec<String>,
+    #[builder(each = "label")]
+    labels: HashMap<String, String>,
+    #[builder(setter(into), args(name = "out"))]
+    output: Option<String>,
+    #[builder(args(skip), default)]
+    internal: u8,
+}
+
+#[derive(Builder, Debug)]
+#[builder(args, pattern = "owned")]
+pub struct Owned {
+    #[builder(default)]
+    dry_run: bool,
+}
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+fn main() {
+    let tool = ToolBuilder::from_args(args(&[
+        "--listen-addr=0.0.0.0:80",
+        "--workers",
+        "8",
+        "--verbose",
+        "--inputs",
+        "a.txt",
+        "--inputs=b.txt",
+        "--labels",
+        "env=prod",
+        "--out",
+        "result.json",
+    ]))
+    .unwrap()
+    .build()
+    .unwrap();
+    assert_eq!(tool.listen_addr, "0.0.0.0:80");
+    assert_eq!(tool.workers, 8);
+    assert!(tool.verbose);
+    assert_eq!(tool.inputs, vec!["a.txt", "b.txt"]);
+    assert_eq!(tool.labels["env"], "prod");
+    assert_eq!(tool.output.as_deref(), Some("result.json"));
+    assert_eq!(tool.internal, 0);
+
+    let tool = ToolBuilder::from_args(args(&["--listen-addr", "x", "--verbose=false"]))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert!(!tool.verbose);
+
+    let err = ToolBuilder::from_args(args(&["--workers", "many"])).err().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "argument `--workers` is invalid: invalid digit found in string"
+    );
+    let err = ToolBuilder::from_args(args(&["--workers"])).err().unwrap();
+    assert_eq!(err, ToolBuilderError::MissingValue { flag: "--workers" });
+    let err = ToolBuilder::from_args(args(&["--internal=1"])).err().unwrap();
+    assert_eq!(err.to_string(), "unexpected argument `--internal=1`");
+    let err = ToolBuilder::from_args(args(&["input.txt"])).err().unwrap();
+    assert_eq!(err.to_string(), "unexpected argument `input.txt`");
+
+    let err = ToolBuilder::from_args(args(&["--help"])).err().unwrap();
+    assert_eq!(err, ToolBuilderError::HelpRequested);
+    assert_eq!(err.to_string(), ToolBuilder::HELP);
+    assert_eq!(
+        ToolBuilder::HELP,
+        "Options:
+  --listen-addr <VALUE>    Address to listen on.
+  --workers <VALUE>        Number of worker threads.
+  --verbose                Print more output.
+  --inputs <VALUE>...      Files to process.
+  --labels <KEY=VALUE>...
+  --out <VALUE>
+  -h, --help               Print this help
+"
+    );
+
+    let owned = OwnedBuilder::from_args(args(&["--dry-run"]))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert!(owned.dry_run);
+}
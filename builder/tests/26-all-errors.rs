@@ -0,0 +1,60 @@
+// build() does not stop at the first problem. Every missing field and every
+// failed check is collected, and if there is more than one they come back
+// together in the `Multiple` variant. `errors()` and `missing_fields()` give
+// structured access whether one or several errors were found.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug)]
+pub struct Connection {
+    host: String,
+    port: u16,
+    timeout: u64,
+    #[builder(default)]
+    retries: u8,
+}
+
+#[derive(Builder, Debug)]
+pub struct Account {
+    #[builder(non_empty)]
+    name: String,
+    #[builder(range(min = 18))]
+    age: u32,
+    email: String,
+}
+
+fn main() {
+    let err = Connection::builder().build().unwrap_err();
+    assert_eq!(err.to_string(), "missing fields: host, port, timeout");
+    assert_eq!(err.missing_fields(), vec!["host", "port", "timeout"]);
+    assert_eq!(err.errors().len(), 3);
+    assert_eq!(
+        err,
+        ConnectionBuilderError::Multiple(vec![
+            ConnectionBuilderError::MissingField { name: "host" },
+            ConnectionBuilderError::MissingField { name: "port" },
+            ConnectionBuilderError::MissingField { name: "timeout" },
+        ])
+    );
+
+    // A single problem is still reported on its own.
+    let err = Connection::builder()
+        .host("localhost".to_owned())
+        .port(5432)
+        .build()
+        .unwrap_err();
+    assert_eq!(err, ConnectionBuilderError::MissingField { name: "timeout" });
+    assert_eq!(err.missing_fields(), vec!["timeout"]);
+    assert_eq!(err.errors(), &[err.clone()]);
+
+    let err = Account::builder()
+        .name(String::new())
+        .age(12)
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "field `email` is not set; field `name` must not be empty; field `age` is out of range `18..`"
+    );
+    assert_eq!(err.missing_fields(), vec!["email"]);
+}
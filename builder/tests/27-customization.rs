@@ -0,0 +1,44 @@
+// The generated names and visibility can be customized. `name` and `vis`
+// rename the builder and set the visibility of the builder, its error type
+// and the constructor, `setter(prefix)` prefixes every setter name and
+// `build_fn(name)` renames build(). A field's `setter(name)` takes precedence
+// over the prefix.
+
+#![deny(private_interfaces)]
+
+mod config {
+    use derive_builder::Builder;
+
+    #[derive(Builder, Debug)]
+    #[builder(
+        name = "ConfigMaker",
+        vis = "pub(crate)",
+        setter(prefix = "with_"),
+        build_fn(name = "finish")
+    )]
+    pub(crate) struct Config {
+        pub(crate) host: String,
+        #[builder(default)]
+        pub(crate) port: u16,
+        #[builder(setter(name = "tag"), each = "label")]
+        pub(crate) labels: Vec<String>,
+    }
+}
+
+use config::{Config, ConfigMaker, ConfigMakerError};
+
+fn main() {
+    let mut maker: ConfigMaker = Config::builder();
+    let config = maker
+        .with_host("localhost".to_owned())
+        .with_port(8080)
+        .label("a".to_owned())
+        .finish()
+        .unwrap();
+    assert_eq!(config.host, "localhost");
+    assert_eq!(config.port, 8080);
+    assert_eq!(config.labels, vec!["a"]);
+
+    let err = Config::builder().finish().unwrap_err();
+    assert_eq!(err, ConfigMakerError::MissingField { name: "host" });
+}
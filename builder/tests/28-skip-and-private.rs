@@ -0,0 +1,47 @@
+// #[builder(skip)] leaves a field out of the builder entirely: it gets no
+// setter and build() fills it from `Default` or from its `default`
+// expression. #[builder(setter(private))] keeps the setter but makes it
+// private, so only methods written next to the struct can set the field.
+
+mod cache {
+    use derive_builder::Builder;
+    use std::collections::HashMap;
+    use std::marker::PhantomData;
+
+    #[derive(Builder, Debug)]
+    pub struct Cache<K> {
+        pub capacity: usize,
+        #[builder(setter(private))]
+        pub shards: usize,
+        #[builder(skip)]
+        pub entries: HashMap<String, String>,
+        #[builder(skip, default = 1)]
+        pub generation: u64,
+        #[builder(skip)]
+        _key: PhantomData<K>,
+    }
+
+    impl<K> CacheBuilder<K> {
+        pub fn per_core(&mut self, cores: usize) -> &mut Self {
+            self.shards(cores * 2)
+        }
+    }
+}
+
+use cache::Cache;
+
+fn main() {
+    let cache: Cache<u32> = Cache::builder()
+        .capacity(64)
+        .per_core(4)
+        .build()
+        .unwrap();
+    assert_eq!(cache.capacity, 64);
+    assert_eq!(cache.shards, 8);
+    assert!(cache.entries.is_empty());
+    assert_eq!(cache.generation, 1);
+
+    let copy = cache.to_builder().capacity(128).build().unwrap();
+    assert_eq!(copy.capacity, 128);
+    assert_eq!(copy.generation, 1);
+}
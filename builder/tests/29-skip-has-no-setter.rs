@@ -0,0 +1,14 @@
+// A skipped field has no setter.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Counter {
+    name: String,
+    #[builder(skip)]
+    hits: u64,
+}
+
+fn main() {
+    let _ = Counter::builder().name("home".to_owned()).hits(3).build();
+}
@@ -0,0 +1,50 @@
+// #[builder(derive(...))] adds derives to the builder, and `struct_attr(...)`
+// and `field_attr(...)` place arbitrary attributes on the builder and its
+// fields. `#[cfg]` attributes of the struct's fields are carried over to
+// everything generated for them, so cfg-gated fields work.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(derive(Clone, Debug, PartialEq), struct_attr(derive(Default)))]
+pub struct Request {
+    #[builder(field_attr(doc = "The full URL."))]
+    url: String,
+    #[builder(each = "header")]
+    headers: Vec<(String, String)>,
+    #[cfg(any())]
+    never: NotAType,
+    #[cfg(any())]
+    #[builder(each = "never_item", range(min = 1))]
+    never_items: Vec<NotAType>,
+    #[cfg(all())]
+    #[builder(default = 30)]
+    timeout: u64,
+}
+
+#[derive(Builder, Debug)]
+#[builder(pattern = "immutable", derive(Clone, Debug))]
+pub struct Immutable {
+    value: u8,
+}
+
+fn main() {
+    let mut template = Request::builder();
+    template.header(("Accept".to_owned(), "*/*".to_owned()));
+
+    let mut first = template.clone();
+    let first = first.url("https://a".to_owned()).build().unwrap();
+    let mut second = template.clone();
+    let second = second.url("https://b".to_owned()).build().unwrap();
+    assert_eq!(first.headers, second.headers);
+    assert_ne!(first, second);
+    assert_eq!(first.timeout, 30);
+    assert_eq!(template, template.clone());
+    assert!(format!("{:?}", template).starts_with("RequestBuilder"));
+
+    let mut empty = RequestBuilder::default();
+    assert!(empty.build().is_err());
+
+    let value = Immutable::builder().value(1).clone().build().unwrap();
+    assert_eq!(value.value, 1);
+}
@@ -0,0 +1,75 @@
+//! Field docs are copied onto the setters generated for the field, and
+//! everything else the derive generates is documented as well, so a builder
+//! in a public API passes `missing_docs`. #[builder(doc = "...")] replaces the
+//! wording, on the builder when given on the struct and on the setters when
+//! given on a field.
+
+#![deny(missing_docs)]
+
+use derive_builder::Builder;
+
+/// An HTTP client.
+#[derive(Builder)]
+#[builder(doc = "Configures a [`Client`] before it is created.")]
+pub struct Client {
+    /// Base URL that every request is relative to.
+    pub base_url: String,
+    /// Seconds to wait for a response.
+    #[builder(default = 30)]
+    pub timeout: u64,
+    /// Headers sent with every request.
+    #[builder(each = "header", doc = "Adds a header sent with every request.")]
+    pub headers: Vec<(String, String)>,
+    /// Times to retry a failed request.
+    #[builder(range(min = 1))]
+    pub retries: Option<u8>,
+}
+
+/// Settings read from the environment or the command line.
+#[derive(Builder)]
+#[builder(env(prefix = "APP_"), args, try_setter)]
+pub struct Settings {
+    /// Client settings.
+    #[builder(sub_builder)]
+    pub client: Client,
+    /// Name of the application.
+    #[builder(non_empty, matches = "app-*")]
+    pub name: String,
+}
+
+/// A point that must have both coordinates.
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Point {
+    /// Horizontal coordinate.
+    pub x: i32,
+    /// Vertical coordinate.
+    #[builder(range(max = 100))]
+    pub y: i32,
+}
+
+/// A command.
+#[derive(Builder)]
+#[builder(build_fn(validate = check), pattern = "owned")]
+pub enum Command {
+    /// Starts the service.
+    Start {
+        /// Seconds to wait first.
+        delay: u64,
+    },
+    /// Stops the service.
+    Stop,
+}
+
+fn check<T>(_builder: &T) -> Result<(), String> {
+    Ok(())
+}
+
+fn main() {
+    let client = Client::builder()
+        .base_url("https://example.com".to_owned())
+        .header(("Accept".to_owned(), "*/*".to_owned()))
+        .build()
+        .unwrap();
+    assert_eq!(client.timeout, 30);
+}
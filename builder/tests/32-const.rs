@@ -0,0 +1,43 @@
+// #[builder(const)] makes the constructor, the setters and build() `const fn`
+// so that a builder can fill a `static` or `const`. build() returns the value
+// directly and panics if a required field is missing. Field types must be
+// `Copy` since const fns cannot run destructors.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(const)]
+pub struct Board {
+    name: &'static str,
+    #[builder(default = 16_000_000)]
+    clock_hz: u32,
+    led_pin: Option<u8>,
+    #[builder(skip, default = 0xFF)]
+    reserved: u8,
+}
+
+#[derive(Builder, Debug)]
+#[builder(const)]
+pub struct Pair<T: Copy> {
+    first: T,
+    second: T,
+}
+
+static BOARD: Board = Board::builder().name("feather").led_pin(13).build();
+
+const PAIR: Pair<u16> = Pair::builder().first(1).second(2).build();
+
+fn main() {
+    assert_eq!(BOARD.name, "feather");
+    assert_eq!(BOARD.clock_hz, 16_000_000);
+    assert_eq!(BOARD.led_pin, Some(13));
+    assert_eq!(BOARD.reserved, 0xFF);
+    assert_eq!((PAIR.first, PAIR.second), (1, 2));
+
+    // The builder still works at runtime.
+    let board = Board::builder().name("pico").clock_hz(125_000_000).build();
+    assert_eq!(board.led_pin, None);
+
+    let result = std::panic::catch_unwind(|| Board::builder().build());
+    assert!(result.is_err());
+}
@@ -0,0 +1,16 @@
+// A required field that is not set in a const builder fails at compile time.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(const)]
+pub struct Board {
+    name: &'static str,
+    clock_hz: u32,
+}
+
+static BOARD: Board = Board::builder().name("feather").build();
+
+fn main() {
+    let _ = &BOARD;
+}
@@ -0,0 +1,98 @@
+// Every builder describes its fields in a FIELDS constant of the shared
+// `derive_builder::BuilderFieldInfo`: name, type as written, how build()
+// treats the field, and its doc comment. Builders can also report which
+// fields are still unset, except for the required fields of typestate
+// builders, which their type tracks instead. If derive_builder is renamed,
+// #[builder(crate = path)] tells the derive where to find these types.
+
+use derive_builder::{Builder, BuilderFieldInfo, BuilderFieldKind};
+use std::collections::HashMap;
+
+#[derive(Builder)]
+pub struct Server {
+    /// Address to listen on.
+    ///
+    /// Either a host name or an IP address.
+    host: String,
+    port: Option<u16>,
+    #[builder(default = 4)]
+    workers: usize,
+    #[builder(each = "header")]
+    headers: HashMap<String, Vec<String>>,
+    #[builder(skip)]
+    requests: u64,
+}
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Job {
+    command: &'static str,
+    retries: Option<u8>,
+    #[builder(default)]
+    verbose: bool,
+}
+
+mod renamed {
+    extern crate derive_builder as builder;
+
+    #[derive(builder::Builder)]
+    #[builder(crate = builder)]
+    pub struct Plain {
+        pub value: u8,
+    }
+}
+
+fn describe(fields: &[BuilderFieldInfo]) -> Vec<&'static str> {
+    fields.iter().map(|field| field.name).collect()
+}
+
+fn main() {
+    let fields = ServerBuilder::FIELDS;
+    assert_eq!(fields.len(), 4);
+
+    assert_eq!(fields[0].name, "host");
+    assert_eq!(fields[0].ty, "String");
+    assert_eq!(fields[0].kind, BuilderFieldKind::Required);
+    assert_eq!(
+        fields[0].doc,
+        "Address to listen on.\n\nEither a host name or an IP address."
+    );
+
+    assert_eq!(fields[1].ty, "Option<u16>");
+    assert_eq!(fields[1].kind, BuilderFieldKind::Optional);
+    assert_eq!(fields[1].doc, "");
+    assert_eq!(fields[2].kind, BuilderFieldKind::Defaulted);
+    assert_eq!(fields[3].ty, "HashMap<String, Vec<String>>");
+    assert_eq!(fields[3].kind, BuilderFieldKind::Each);
+
+    let mut builder = Server::builder();
+    assert_eq!(
+        builder.unset_fields(),
+        vec!["host", "port", "workers", "headers"]
+    );
+    builder.host("localhost".to_owned()).workers(8);
+    assert!(builder.is_host_set());
+    assert!(!builder.is_port_set());
+    assert_eq!(builder.unset_fields(), vec!["port", "headers"]);
+    builder.header("Accept".to_owned(), vec!["*/*".to_owned()]);
+    assert!(builder.is_headers_set());
+
+    assert_eq!(describe(JobBuilder::FIELDS), ["command", "retries", "verbose"]);
+    assert_eq!(JobBuilder::FIELDS[0].ty, "&'static str");
+    assert_eq!(describe(ServerBuilder::FIELDS)[0], "host");
+
+    assert_eq!(
+        renamed::PlainBuilder::FIELDS[0].kind,
+        BuilderFieldKind::Required
+    );
+
+    let job = Job::builder().retries(2);
+    assert!(job.is_retries_set());
+    assert_eq!(job.unset_fields(), ["verbose"]);
+    let job = job.command("make");
+    assert!(!job.is_verbose_set());
+    assert_eq!(job.build().command, "make");
+
+    let server = builder.build().unwrap();
+    assert_eq!(server.requests, 0);
+}
@@ -0,0 +1,84 @@
+// Every stored field gets a `get_<field>()` returning what was set, if
+// anything, and a `clear_<field>()` undoing it. `reset()` clears the whole
+// builder. Both follow the builder's pattern, like the setters. A field
+// named `reset` needs its setter renamed.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug, PartialEq)]
+pub struct Retry {
+    attempts: u32,
+}
+
+#[derive(Builder, Debug)]
+pub struct Request {
+    url: String,
+    timeout: Option<u64>,
+    #[builder(each = "header")]
+    headers: Vec<(String, String)>,
+    #[builder(sub_builder)]
+    retry: Retry,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(pattern = "owned")]
+pub struct Span(u32, u32);
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(const)]
+pub struct Limits {
+    max: u32,
+    min: Option<u32>,
+}
+
+#[derive(Builder, Debug)]
+pub struct Session {
+    #[builder(setter(name = "set_reset"), default)]
+    reset: bool,
+}
+
+const LIMITS: Limits = Limits::builder().max(10).clear_max().max(20).build();
+
+fn main() {
+    let mut builder = Request::builder();
+    assert_eq!(builder.get_url(), None);
+    builder
+        .url("https://example.com".to_owned())
+        .timeout(30)
+        .header(("Accept".to_owned(), "*/*".to_owned()));
+    builder.retry().attempts(3);
+    assert_eq!(
+        builder.get_url().map(String::as_str),
+        Some("https://example.com")
+    );
+    assert_eq!(builder.get_timeout(), Some(&30));
+    assert_eq!(builder.get_headers().map(Vec::len), Some(1));
+    assert_eq!(builder.get_retry().get_attempts(), Some(&3));
+
+    builder.clear_timeout().clear_headers();
+    assert_eq!(builder.get_timeout(), None);
+    assert_eq!(builder.get_headers(), None);
+    let request = builder.build().unwrap();
+    assert_eq!(request.timeout, None);
+    assert!(request.headers.is_empty());
+
+    builder.clear_retry();
+    assert_eq!(builder.get_retry().get_attempts(), None);
+    builder.reset();
+    assert_eq!(builder.get_url(), None);
+    assert_eq!(builder.unset_fields(), vec!["url", "timeout", "headers"]);
+
+    let builder = Span::builder()._0(1)._1(2).clear_1();
+    assert_eq!(builder.get_0(), Some(&1));
+    assert_eq!(builder.get_1(), None);
+    let span = builder._1(5).build().unwrap();
+    assert_eq!(span, Span(1, 5));
+    assert!(Span::builder()._0(1).reset().get_0().is_none());
+
+    assert_eq!(LIMITS, Limits { max: 20, min: None });
+
+    let mut session = Session::builder();
+    session.set_reset(true);
+    assert_eq!(session.get_reset(), Some(&true));
+    assert!(!session.reset().build().unwrap().reset);
+}
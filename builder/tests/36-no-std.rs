@@ -0,0 +1,103 @@
+// #[builder(crate_root = core)] makes the generated code use only `::core`
+// paths so that it compiles in a `#![no_std]` crate without an allocator.
+// build() then stops at the first error instead of collecting all of them,
+// and a validation function returns a `&'static str` message.
+// #[builder(crate_root = alloc)] takes collections from `::alloc` and keeps
+// reporting every error.
+
+#![no_std]
+
+extern crate alloc;
+// Links the runtime for the test without putting `std` in scope.
+extern crate std as runtime;
+
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use derive_builder::Builder;
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(crate_root = core)]
+pub struct Uart {
+    baud: u32,
+    #[builder(default = 8, range(min = 5, max = 9))]
+    data_bits: u8,
+    parity: Option<bool>,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(crate_root = core, build_fn(validate = check_board))]
+pub struct Board {
+    #[builder(matches = "rev?")]
+    revision: &'static str,
+    #[builder(sub_builder)]
+    uart: Uart,
+}
+
+fn check_board(builder: &BoardBuilder) -> Result<(), &'static str> {
+    match builder.get_revision() {
+        Some(&"rev0") => Err("rev0 is not supported"),
+        _ => Ok(()),
+    }
+}
+
+#[derive(Builder, Debug)]
+#[builder(crate_root = alloc)]
+pub struct Packet {
+    id: u16,
+    #[builder(each = "byte")]
+    payload: Vec<u8>,
+    label: String,
+}
+
+fn main() {
+    let uart = Uart::builder().baud(115_200).build().unwrap();
+    assert_eq!(uart.data_bits, 8);
+
+    let err = Uart::builder().data_bits(4).build().unwrap_err();
+    assert_eq!(err, UartBuilderError::MissingField { name: "baud" });
+    assert_eq!(
+        err.errors(),
+        [UartBuilderError::MissingField { name: "baud" }]
+    );
+
+    let mut board = Board::builder();
+    board.revision("revB");
+    board.uart().baud(9600);
+    assert_eq!(board.build().unwrap().uart.baud, 9600);
+
+    board.uart().data_bits(10);
+    let err = board.build().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "field `uart.data_bits` is out of range `5..=9`"
+    );
+
+    board.revision("rev0");
+    let err = board.build().unwrap_err();
+    assert_eq!(
+        err,
+        BoardBuilderError::ValidationFailed {
+            message: "rev0 is not supported"
+        }
+    );
+
+    board.revision("v2");
+    board.uart().data_bits(8);
+    assert_eq!(
+        board.build().unwrap_err(),
+        BoardBuilderError::PatternMismatch {
+            field: "revision",
+            pattern: "rev?"
+        }
+    );
+
+    let mut packet = Packet::builder();
+    packet.byte(1).byte(2);
+    assert_eq!(packet.unset_fields(), ["id", "label"]);
+    let err = packet.build().unwrap_err();
+    assert_eq!(err.missing_fields(), ["id", "label"]);
+    assert_eq!(err.to_string(), "missing fields: id, label");
+
+    let packet = packet.id(7).label("ping".to_string()).build().unwrap();
+    assert_eq!(packet.payload, [1, 2]);
+}
@@ -0,0 +1,114 @@
+// #[builder(required_ctor)] adds builder_with() alongside builder(). It takes
+// every required field in declaration order and returns its own builder,
+// e.g. `ConnectionBuilderWith`, which only has setters for the optional,
+// defaulted and `each` fields. Since nothing can be missing, its build()
+// returns the value directly unless validation or field checks can still
+// fail. builder() is unchanged and still reports required fields that are
+// not set.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(required_ctor)]
+pub struct Connection {
+    host: String,
+    port: u16,
+    #[builder(default = 3)]
+    retries: u32,
+    timeout: Option<u64>,
+    #[builder(each = "tag")]
+    tags: Vec<String>,
+}
+
+#[derive(Builder, Debug)]
+#[builder(required_ctor, pattern = "owned")]
+pub struct Span(#[builder(setter(into))] String, u32, Option<u32>);
+
+#[derive(Builder, Debug)]
+#[builder(required_ctor)]
+pub struct Pool {
+    #[builder(range(min = 1))]
+    size: usize,
+    name: Option<String>,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(required_ctor, pattern = "immutable")]
+pub struct Pair<'a, T: Clone> {
+    first: &'a T,
+    second: Option<T>,
+}
+
+#[derive(Builder, Debug)]
+pub struct Service {
+    #[builder(sub_builder)]
+    connection: Connection,
+}
+
+fn main() {
+    let connection = Connection::builder_with("localhost".to_owned(), 8080)
+        .timeout(30)
+        .tag("primary".to_owned())
+        .build();
+    assert_eq!(
+        connection,
+        Connection {
+            host: "localhost".to_owned(),
+            port: 8080,
+            retries: 3,
+            timeout: Some(30),
+            tags: vec!["primary".to_owned()],
+        }
+    );
+
+    let mut builder: ConnectionBuilderWith = Connection::builder_with("db".to_owned(), 5432);
+    builder.retries(5).timeout(10);
+    assert_eq!(builder.get_retries(), Some(&5));
+    let built: Connection = builder.build();
+    assert_eq!((built.port, built.retries, built.timeout), (5432, 5, Some(10)));
+    builder.clear_timeout();
+    assert_eq!(builder.build().timeout, None);
+    builder.reset();
+    assert_eq!(builder.build().host, "db");
+
+    // The full builder picks up the required fields with everything else.
+    let mut full = ConnectionBuilder::from(Connection::builder_with("db".to_owned(), 5432));
+    assert_eq!(full.get_host().map(String::as_str), Some("db"));
+    full.port(5433);
+    assert_eq!(full.build().unwrap().port, 5433);
+
+    let err = Connection::builder().port(80).build().unwrap_err();
+    assert_eq!(err, ConnectionBuilderError::MissingField { name: "host" });
+
+    let span: Span = Span::builder_with("main", 4)._2(7).build();
+    assert_eq!((span.0.as_str(), span.1, span.2), ("main", 4, Some(7)));
+
+    assert!(Pool::builder_with(4).build().is_ok());
+    assert_eq!(
+        Pool::builder_with(0)
+            .name("workers".to_owned())
+            .build()
+            .err(),
+        Some(PoolBuilderError::OutOfRange {
+            field: "size",
+            range: "1..",
+        })
+    );
+
+    let builder = Pair::builder_with(&1);
+    let pair = builder.second(2).build();
+    assert_eq!(pair, Pair { first: &1, second: Some(2) });
+    assert_eq!(builder.build().second, None);
+
+    let mut service = Service::builder();
+    service.connection().host("cache".to_owned()).port(6379);
+    assert_eq!(service.build().unwrap().connection.port, 6379);
+    service.connection().clear_port();
+    assert_eq!(
+        service.build().unwrap_err().to_string(),
+        "field `connection.port` is not set"
+    );
+
+    let copy = connection.to_builder().build().unwrap();
+    assert_eq!(copy.host, "localhost");
+}
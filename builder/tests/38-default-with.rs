@@ -0,0 +1,135 @@
+// #[builder(default_with = ...)] computes a field that was not set from the
+// rest of the builder, with a closure or the path of a function taking
+// `&FooBuilder` and returning a `Result`. build() runs these functions after
+// plain defaults are filled in and the fields they read have been computed,
+// and reports their errors as `DefaultFailed`. With `build_fn(error = Type)`
+// the functions return that type and the variant holds their error as it is.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug, PartialEq)]
+pub struct Client {
+    #[builder(default_with = |b: &ClientBuilder| -> Result<u64, String> {
+        let attempt = b.attempt_timeout_ms.ok_or("no attempt timeout")?;
+        Ok(attempt * u64::from(b.retries.unwrap_or(1)))
+    })]
+    total_timeout_ms: u64,
+    retries: u32,
+    #[builder(default_with = |b: &ClientBuilder| match b.get_retries() {
+        Some(0) => Err("retries must be positive"),
+        _ => Ok(500),
+    })]
+    attempt_timeout_ms: u64,
+    #[builder(default_with = user_agent)]
+    user_agent: Option<String>,
+}
+
+mod defaults {
+    use derive_builder::Builder;
+
+    #[derive(Builder, Debug)]
+    pub struct Client {
+        #[builder(default = 3)]
+        pub retries: u32,
+        #[builder(default_with = |b: &ClientBuilder| {
+            b.get_retries().map(|r| r * 100).ok_or("no retries")
+        })]
+        pub timeout: u32,
+    }
+}
+
+mod typed {
+    use derive_builder::Builder;
+    use std::num::ParseIntError;
+
+    #[derive(Builder, Debug)]
+    #[builder(build_fn(error = ParseIntError))]
+    pub struct Limits {
+        pub max_connections: Option<String>,
+        #[builder(default_with = |b: &LimitsBuilder| match b.get_max_connections() {
+            Some(max) => max.parse::<u32>().map(|max| max / 2),
+            _ => Ok(8),
+        })]
+        pub max_idle: u32,
+    }
+}
+
+fn user_agent(builder: &ClientBuilder) -> Result<Option<String>, std::fmt::Error> {
+    Ok(builder
+        .retries
+        .map(|retries| format!("client (retries={})", retries)))
+}
+
+fn main() {
+    let client = Client::builder().retries(3).build().unwrap();
+    assert_eq!(
+        client,
+        Client {
+            total_timeout_ms: 1500,
+            retries: 3,
+            attempt_timeout_ms: 500,
+            user_agent: Some("client (retries=3)".to_owned()),
+        }
+    );
+
+    // Values that are set are used as they are, including by the functions
+    // computing other fields.
+    let client = Client::builder()
+        .retries(2)
+        .attempt_timeout_ms(100)
+        .user_agent("curl".to_owned())
+        .build()
+        .unwrap();
+    assert_eq!(client.total_timeout_ms, 200);
+    assert_eq!(client.user_agent.as_deref(), Some("curl"));
+
+    let err = Client::builder().retries(0).build().unwrap_err();
+    assert_eq!(
+        err.errors(),
+        [
+            ClientBuilderError::DefaultFailed {
+                field: "attempt_timeout_ms",
+                message: "retries must be positive".to_owned(),
+            },
+            ClientBuilderError::DefaultFailed {
+                field: "total_timeout_ms",
+                message: "no attempt timeout".to_owned(),
+            },
+        ]
+    );
+    assert_eq!(
+        err.to_string(),
+        "field `attempt_timeout_ms` could not be computed: retries must be positive; \
+         field `total_timeout_ms` could not be computed: no attempt timeout"
+    );
+
+    // Fields with a plain default hold it when the functions run.
+    let client = defaults::Client::builder().build().unwrap();
+    assert_eq!(client.retries, 3);
+    assert_eq!(client.timeout, 300);
+
+    let limits = typed::Limits::builder().build().unwrap();
+    assert_eq!(limits.max_idle, 8);
+    let err = typed::Limits::builder()
+        .max_connections("many".to_owned())
+        .build()
+        .unwrap_err();
+    let parse_err = "many".parse::<u32>().unwrap_err();
+    assert_eq!(
+        err,
+        typed::LimitsBuilderError::DefaultFailed {
+            field: "max_idle",
+            error: parse_err.clone(),
+        }
+    );
+    assert_eq!(
+        err.to_string(),
+        format!("field `max_idle` could not be computed: {}", parse_err)
+    );
+
+    // The builder itself is left untouched.
+    let mut builder = Client::builder();
+    builder.retries(4);
+    builder.build().unwrap();
+    assert!(!builder.is_total_timeout_ms_set());
+}
@@ -0,0 +1,14 @@
+// Functions given to #[builder(default_with = ...)] must not read each other
+// in a cycle, since neither could be computed first.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Window {
+    #[builder(default_with = |b: &WindowBuilder| b.height.map(|h| h * 2).ok_or("no height"))]
+    width: u32,
+    #[builder(default_with = |b: &WindowBuilder| b.width.map(|w| w / 2).ok_or("no width"))]
+    height: u32,
+}
+
+fn main() {}
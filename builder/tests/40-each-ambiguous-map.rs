@@ -0,0 +1,17 @@
+// With two or more type arguments, a collection that is not one of the known
+// maps could hold either entries or its first type argument, so `each`
+// refuses to guess.
+
+use derive_builder::Builder;
+use std::marker::PhantomData;
+
+#[derive(Clone, Default)]
+pub struct LayerMap<K, V>(Vec<(K, V)>, PhantomData<K>);
+
+#[derive(Builder)]
+pub struct Scene {
+    #[builder(each = "layer")]
+    layers: LayerMap<u8, String>,
+}
+
+fn main() {}
@@ -0,0 +1,103 @@
+// Sub-builders work whatever the pattern of the parent and of the child, and
+// whatever the child's builder and build() are named: the parent reaches the
+// child's builder through a hidden trait that every struct builder
+// implements. A parent that keeps its builder
+// intact, by the mutable or immutable pattern, builds a clone of the child,
+// so the child's fields must be `Clone` in that case.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug, PartialEq)]
+pub struct Mutable {
+    value: u32,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(pattern = "owned", build_fn(name = "finish"))]
+pub struct Owned {
+    value: String,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(pattern = "immutable")]
+pub struct Immutable {
+    value: u8,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(name = "LimitsConfig")]
+pub struct Limits {
+    max: u32,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(pattern = "owned")]
+pub struct OwnedParent {
+    #[builder(sub_builder)]
+    mutable: Mutable,
+    #[builder(sub_builder)]
+    owned: Owned,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(pattern = "immutable")]
+pub struct ImmutableParent {
+    #[builder(sub_builder)]
+    mutable: Mutable,
+    #[builder(sub_builder)]
+    immutable: Immutable,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+pub struct MutableParent {
+    #[builder(sub_builder)]
+    owned: Owned,
+    #[builder(sub_builder)]
+    limits: Limits,
+}
+
+#[derive(Builder, Debug, PartialEq)]
+#[builder(typestate)]
+pub struct TypestateParent {
+    name: &'static str,
+    #[builder(sub_builder)]
+    mutable: Mutable,
+}
+
+fn main() {
+    let mut parent = OwnedParent::builder();
+    parent.mutable().value(1);
+    *parent.owned() = Owned::builder().value("a".to_owned());
+    let parent = parent.build().unwrap();
+    assert_eq!(parent.mutable.value, 1);
+    assert_eq!(parent.owned.value, "a");
+
+    let mut parent = ImmutableParent::builder();
+    parent.mutable().value(2);
+    *parent.immutable() = parent.immutable().value(3);
+    let built = parent.build().unwrap();
+    assert_eq!((built.mutable.value, built.immutable.value), (2, 3));
+    // The immutable parent is left as it was.
+    assert_eq!(parent.build().unwrap(), built);
+
+    let mut parent = MutableParent::builder();
+    *parent.owned() = Owned::builder().value("b".to_owned());
+    let limits: &mut LimitsConfig = parent.limits();
+    limits.max(10);
+    assert_eq!(parent.build().unwrap().owned.value, "b");
+    assert_eq!(parent.build().unwrap().limits, Limits { max: 10 });
+    parent.limits().clear_max();
+    assert_eq!(
+        parent.build().unwrap_err().to_string(),
+        "field `limits.max` is not set"
+    );
+    assert_eq!(
+        MutableParent::builder().build().unwrap_err().to_string(),
+        "field `owned.value` is not set; field `limits.max` is not set"
+    );
+
+    let mut parent = TypestateParent::builder().name("t");
+    parent.mutable().value(4);
+    let parent = parent.build().unwrap();
+    assert_eq!(parent.mutable.value, 4);
+}
@@ -0,0 +1,64 @@
+// Fields with a private setter are not read by from_args() or from_env(), and
+// are left out of HELP, since only methods written on the builder may set
+// them. Naming the argument or variable explicitly opts such a field back in.
+
+use derive_builder::Builder;
+use std::collections::HashMap;
+
+#[derive(Builder, Debug)]
+#[builder(args, env(prefix = "APP_"))]
+pub struct Service {
+    /// Name of the service.
+    name: String,
+    /// Secret used to sign requests.
+    #[builder(setter(private), default)]
+    secret: String,
+    /// Token used to call other services.
+    #[builder(
+        setter(private),
+        default,
+        args(name = "token"),
+        env(name = "APP_TOKEN")
+    )]
+    token: String,
+}
+
+fn args(args: &[&str]) -> Vec<String> {
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+fn main() {
+    let err = ServiceBuilder::from_args(args(&["--name=api", "--secret=hunter2"]))
+        .err()
+        .unwrap();
+    assert_eq!(
+        err,
+        ServiceBuilderError::UnknownArgument {
+            argument: "--secret=hunter2".to_owned()
+        }
+    );
+    assert!(!ServiceBuilder::HELP.contains("secret"));
+    assert!(ServiceBuilder::HELP.contains("--token"));
+
+    let service = ServiceBuilder::from_args(args(&["--name=api", "--token=abc"]))
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(service.token, "abc");
+
+    let vars: HashMap<String, String> = [
+        ("APP_NAME", "api"),
+        ("APP_SECRET", "hunter2"),
+        ("APP_TOKEN", "abc"),
+    ]
+    .into_iter()
+    .map(|(key, value)| (key.to_owned(), value.to_owned()))
+    .collect();
+    let service = ServiceBuilder::from_env_map(&vars)
+        .unwrap()
+        .build()
+        .unwrap();
+    assert_eq!(service.name, "api");
+    assert_eq!(service.secret, "");
+    assert_eq!(service.token, "abc");
+}
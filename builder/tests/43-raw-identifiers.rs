@@ -0,0 +1,42 @@
+// Raw identifiers are named without their `r#` prefix wherever the builder
+// reports a field by name: in errors, `unset_fields()` and `FIELDS`.
+
+use derive_builder::Builder;
+
+#[derive(Builder, Debug)]
+pub struct Token {
+    r#type: String,
+    #[builder(range(max = 10))]
+    r#loop: u8,
+}
+
+#[derive(Builder, Debug)]
+#[builder(typestate)]
+pub struct Item {
+    r#type: u8,
+}
+
+fn main() {
+    let mut builder = Token::builder();
+    assert_eq!(builder.unset_fields(), ["type", "loop"]);
+    assert_eq!(TokenBuilder::FIELDS[0].name, "type");
+
+    let err = builder.build().unwrap_err();
+    assert_eq!(err.missing_fields(), ["type", "loop"]);
+    assert_eq!(err.to_string(), "missing fields: type, loop");
+
+    let err = builder
+        .r#type("ident".to_owned())
+        .r#loop(11)
+        .build()
+        .unwrap_err();
+    assert_eq!(
+        err,
+        TokenBuilderError::OutOfRange {
+            field: "loop",
+            range: "..=10"
+        }
+    );
+
+    assert_eq!(Item::builder().r#type(1).build().r#type, 1);
+}
@@ -0,0 +1,12 @@
+// A setter may not take the name of a method the builder generates itself,
+// such as merge(). The derive points at the field and suggests renaming it.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Layer {
+    name: String,
+    merge: bool,
+}
+
+fn main() {}
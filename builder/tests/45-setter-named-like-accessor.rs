@@ -0,0 +1,19 @@
+// Setters may not take the names of reset(), or of the getters, clear_ and
+// is_<field>_set methods generated for other fields. The derive points at the
+// field whose setter collides.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+pub struct Session {
+    user: String,
+    reset: bool,
+}
+
+#[derive(Builder)]
+pub struct Server {
+    host: String,
+    get_host: bool,
+}
+
+fn main() {}
@@ -0,0 +1,20 @@
+// A `sub_builder` field needs a child whose builder can be stored half-built,
+// which typestate and const builders cannot be, so they do not implement the
+// trait that sub-builders are reached through. The error names the field's
+// type.
+
+use derive_builder::Builder;
+
+#[derive(Builder)]
+#[builder(typestate)]
+pub struct Credentials {
+    user: String,
+}
+
+#[derive(Builder)]
+pub struct Client {
+    #[builder(sub_builder)]
+    credentials: Credentials,
+}
+
+fn main() {}
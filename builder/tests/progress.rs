@@ -10,4 +10,41 @@ fn tests() {
     t.pass("tests/07-repeated-field.rs");
     t.compile_fail("tests/08-unrecognized-attribute.rs");
     t.pass("tests/09-redefined-prelude-types.rs");
+    t.pass("tests/10-typed-error.rs");
+    t.pass("tests/11-typestate.rs");
+    t.compile_fail("tests/12-typestate-missing-field.rs");
+    t.pass("tests/13-default.rs");
+    t.pass("tests/14-pattern.rs");
+    t.pass("tests/15-generics.rs");
+    t.pass("tests/16-enum-and-tuple.rs");
+    t.pass("tests/17-validation.rs");
+    t.pass("tests/18-setter-conversions.rs");
+    t.pass("tests/19-each-collections.rs");
+    t.compile_fail("tests/20-each-wrong-type.rs");
+    t.pass("tests/21-sub-builder.rs");
+    t.pass("tests/22-round-trip.rs");
+    t.pass("tests/23-merge.rs");
+    t.pass("tests/24-env.rs");
+    t.pass("tests/25-args.rs");
+    t.pass("tests/26-all-errors.rs");
+    t.pass("tests/27-customization.rs");
+    t.pass("tests/28-skip-and-private.rs");
+    t.compile_fail("tests/29-skip-has-no-setter.rs");
+    t.pass("tests/30-forward-attrs.rs");
+    t.pass("tests/31-docs.rs");
+    t.pass("tests/32-const.rs");
+    t.compile_fail("tests/33-const-missing-field.rs");
+    t.pass("tests/34-introspection.rs");
+    t.pass("tests/35-clear-and-get.rs");
+    t.pass("tests/36-no-std.rs");
+    t.pass("tests/37-required-ctor.rs");
+    t.pass("tests/38-default-with.rs");
+    t.compile_fail("tests/39-default-with-cycle.rs");
+    t.compile_fail("tests/40-each-ambiguous-map.rs");
+    t.pass("tests/41-sub-builder-patterns.rs");
+    t.pass("tests/42-private-not-from-sources.rs");
+    t.pass("tests/43-raw-identifiers.rs");
+    t.compile_fail("tests/44-setter-named-merge.rs");
+    t.compile_fail("tests/45-setter-named-like-accessor.rs");
+    t.compile_fail("tests/46-sub-builder-typestate-child.rs");
 }